@@ -0,0 +1,4 @@
+pub mod runner;
+mod server;
+
+pub use self::runner::run;
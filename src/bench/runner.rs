@@ -0,0 +1,102 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crossbeam::channel;
+use prettytable::{row, Table};
+use super::server;
+use crate::fetcher::client::{HttpClient, RUNTIME};
+use crate::fetcher::AuthConfig;
+use crate::store::TaskStore;
+use crate::worker::{AsyncWorker, Budget, BudgetConfig, CrawlMonitor, DedupConfig, DuplicateDetector, RunControl, TaskInfo, TaskStatus, Worker};
+
+/// 在本地测试站点上分别运行线程引擎和异步引擎，对比每秒抓取页面数
+pub fn run(pages: usize, latency_ms: u64, concurrency: usize) {
+    let addr = RUNTIME
+        .block_on(server::serve(pages, Duration::from_millis(latency_ms)))
+        .expect("Failed to start benchmark server");
+    let seeds: Vec<String> = (0..pages).map(|i| format!("http://{}/page/{}", addr, i)).collect();
+    println!("Benchmarking {} pages on http://{} with {}ms latency", pages, addr, latency_ms);
+
+    let mut table = Table::new();
+    table.add_row(row!["Engine", "Workers", "Pages", "Elapsed (s)", "Pages/sec"]);
+
+    let num_threads = num_cpus::get();
+    let elapsed = run_thread_engine(&seeds, num_threads);
+    table.add_row(row!["thread", num_threads, pages, format!("{:.2}", elapsed.as_secs_f64()), format!("{:.1}", pages as f64 / elapsed.as_secs_f64())]);
+
+    let elapsed = run_async_engine(&seeds, concurrency);
+    table.add_row(row!["async", concurrency, pages, format!("{:.2}", elapsed.as_secs_f64()), format!("{:.1}", pages as f64 / elapsed.as_secs_f64())]);
+
+    table.printstd();
+}
+
+// 深度为 1，只抓取种子页面，保证两种引擎的抓取量相同
+fn bench_task_info() -> Arc<Mutex<TaskInfo>> {
+    let mut task_info = TaskInfo::new("bench".to_string(), 1);
+    task_info.status = TaskStatus::Running;
+    Arc::new(Mutex::new(task_info))
+}
+
+// 性能测试不限制抓取预算
+fn bench_budget() -> Arc<Budget> {
+    Arc::new(Budget::new(BudgetConfig::default(), &[]))
+}
+
+// 测试站点的页面内容相同，性能测试不检测近似重复
+fn bench_dedup() -> Arc<DuplicateDetector> {
+    Arc::new(DuplicateDetector::new(DedupConfig { enabled: false, ..DedupConfig::default() }, Vec::new()))
+}
+
+// 性能测试的抓取结果只保存在内存数据库中
+fn bench_store() -> Arc<TaskStore> {
+    Arc::new(TaskStore::open(":memory:").expect("Failed to open in-memory store"))
+}
+
+fn run_thread_engine(seeds: &[String], num_threads: usize) -> Duration {
+    let http_client = Arc::new(HttpClient::new(None, AuthConfig::default()));
+    let visited = Arc::new(Mutex::new(HashSet::new()));
+    let task_info = bench_task_info();
+    let (sender, _receiver) = channel::unbounded();
+    let store = bench_store();
+    let budget = bench_budget();
+    let monitor = Arc::new(CrawlMonitor::new(0));
+    let dedup = bench_dedup();
+
+    let started = Instant::now();
+    let handles: Vec<_> = seeds
+        .chunks(seeds.len().div_ceil(num_threads))
+        .map(|chunk| {
+            let worker = Worker::new(
+                Arc::clone(&http_client),
+                Arc::new(Mutex::new(chunk.iter().map(|url| (url.clone(), 1)).collect::<VecDeque<_>>())),
+                Arc::clone(&visited),
+                Arc::clone(&task_info),
+                sender.clone(),
+                Arc::clone(&store),
+                Arc::clone(&budget),
+                Arc::clone(&monitor),
+                Arc::new(RunControl::default()),
+                Arc::clone(&dedup),
+                None,
+            );
+            thread::spawn(move || worker.run())
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    started.elapsed()
+}
+
+fn run_async_engine(seeds: &[String], concurrency: usize) -> Duration {
+    let http_client = Arc::new(HttpClient::new(None, AuthConfig::default()));
+    let visited = Arc::new(Mutex::new(HashSet::new()));
+    let (sender, _receiver) = channel::unbounded();
+    let seeds = seeds.iter().map(|url| (url.clone(), 1)).collect();
+    let worker = AsyncWorker::new(http_client, seeds, visited, bench_task_info(), sender, bench_store(), bench_budget(), Arc::new(CrawlMonitor::new(0)), Arc::new(RunControl::default()), bench_dedup(), None, concurrency);
+
+    let started = Instant::now();
+    worker.run();
+    started.elapsed()
+}
@@ -0,0 +1,71 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// 每个页面包含的链接数
+const LINKS_PER_PAGE: usize = 10;
+
+/// 本地测试站点：`/page/<i>` 共 pages 个页面，每个页面链接到其他若干页面，响应前等待 latency 模拟网络延迟
+pub async fn serve(pages: usize, latency: Duration) -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, pages, latency));
+        }
+    });
+
+    Ok(addr)
+}
+
+// 支持 keep-alive，逐个处理同一连接上的请求
+async fn handle_connection(mut stream: TcpStream, pages: usize, latency: Duration) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let header_end = loop {
+            if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            }
+        };
+        let request: Vec<u8> = buffer.drain(..header_end).collect();
+        let path = String::from_utf8_lossy(&request)
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("/")
+            .to_string();
+
+        tokio::time::sleep(latency).await;
+
+        let (status, body) = match path.strip_prefix("/page/").and_then(|i| i.parse::<usize>().ok()) {
+            Some(index) if index < pages => ("200 OK", render_page(index, pages)),
+            _ => ("404 Not Found", String::from("<html><body>Not Found</body></html>")),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if stream.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn render_page(index: usize, pages: usize) -> String {
+    let links = (1..=LINKS_PER_PAGE)
+        .map(|step| format!("<li><a href=\"/page/{}\">Page {}</a></li>", (index * 7 + step) % pages, step))
+        .collect::<String>();
+    let paragraph = "The quick brown fox jumps over the lazy dog. ".repeat(40);
+    format!(
+        "<html><head><title>Page {}</title></head><body><article><h1>Page {}</h1><p>{}</p></article><ul>{}</ul></body></html>",
+        index, index, paragraph, links
+    )
+}
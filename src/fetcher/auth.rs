@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+use reqwest::{Client, RequestBuilder};
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::{Serialize, Deserialize};
+// 日志宏
+use log::info;
+
+const REDACTED: &str = "******";
+
+// 未设置请求超时时间时登录请求的超时时间，登录服务无响应时不会一直阻塞任务
+const DEFAULT_LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 认证配置，对应任务配置文件中的 `[auth]` 段
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub basic: Vec<BasicAuth>,
+    #[serde(default)]
+    pub bearer: Vec<BearerAuth>,
+    pub form_login: Option<FormLogin>,
+}
+
+/// 按主机生效的 HTTP Basic 认证
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BasicAuth {
+    pub host: String,
+    pub username: String,
+    pub password: Option<String>,
+}
+
+/// 按主机生效的 Bearer Token 认证
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BearerAuth {
+    pub host: String,
+    pub token: String,
+}
+
+/// 表单登录流程：GET 登录页 -> 填写字段（含 CSRF token）-> POST -> 校验登录结果
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FormLogin {
+    pub login_url: String,
+    /// 表单提交地址，缺省时提交到 login_url
+    pub submit_url: Option<String>,
+    /// 从登录页提取 CSRF token 的 CSS 选择器，取元素的 value 属性（或 content 属性）
+    pub csrf_selector: Option<String>,
+    /// CSRF token 提交时使用的字段名，缺省时使用匹配的 input 元素的 name 属性，其他情况为 csrf_token
+    pub csrf_field: Option<String>,
+    /// 登录后页面中存在该选择器即视为登录成功
+    pub success_selector: Option<String>,
+    /// 登录后最终 URL 以该前缀开头即视为登录成功
+    pub success_redirect: Option<String>,
+    /// 抓取过程中页面命中该选择器即视为已登出，需要重新登录
+    pub logged_out_selector: Option<String>,
+    /// 需要填写的表单字段（字段名 -> 值），TOML 序列化要求表类型字段放在最后
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+}
+
+impl AuthConfig {
+    /// 为请求附加与 URL 主机匹配的 Basic / Bearer 认证信息
+    pub fn apply(&self, request: RequestBuilder, url: &Url) -> RequestBuilder {
+        let Some(host) = url.host_str() else {
+            return request;
+        };
+
+        if let Some(basic) = self.basic.iter().find(|b| host_matches(&b.host, host)) {
+            return request.basic_auth(&basic.username, basic.password.as_ref());
+        }
+        if let Some(bearer) = self.bearer.iter().find(|b| host_matches(&b.host, host)) {
+            return request.bearer_auth(&bearer.token);
+        }
+        request
+    }
+
+    /// 隐去密码、Token 和表单字段值的副本，用于展示配置
+    pub fn redacted(&self) -> Self {
+        let mut auth = self.clone();
+        for basic in &mut auth.basic {
+            basic.password = basic.password.as_ref().map(|_| REDACTED.to_string());
+        }
+        for bearer in &mut auth.bearer {
+            bearer.token = REDACTED.to_string();
+        }
+        if let Some(form_login) = &mut auth.form_login {
+            form_login.fields.values_mut().for_each(|value| *value = REDACTED.to_string());
+        }
+        auth
+    }
+}
+
+impl FormLogin {
+    /// 执行一次完整的表单登录，登录态保存在 client 的 cookie 中；
+    /// 与抓取页面的请求相同，附加 `auth` 中与主机匹配的认证信息，并应用单个请求的超时时间（缺省 30 秒）
+    pub async fn login(&self, client: &Client, auth: &AuthConfig, timeout: Option<Duration>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        info!("Logging in via {}", self.login_url);
+        let timeout = timeout.unwrap_or(DEFAULT_LOGIN_TIMEOUT);
+        let prepare = |request: RequestBuilder, url: &Url| auth.apply(request, url).timeout(timeout);
+
+        let login_url = Url::parse(&self.login_url)?;
+        let login_page = prepare(client.get(login_url.clone()), &login_url).send().await?.error_for_status()?.text().await?;
+
+        let mut form = self.fields.clone();
+        if let Some(csrf_selector) = &self.csrf_selector {
+            let (name, token) = extract_csrf_token(&login_page, csrf_selector)?;
+            let field = self.csrf_field.clone().or(name).unwrap_or_else(|| "csrf_token".to_string());
+            form.insert(field, token);
+        }
+
+        let submit_url = Url::parse(self.submit_url.as_deref().unwrap_or(&self.login_url))?;
+        let response = prepare(client.post(submit_url.clone()), &submit_url).form(&form).send().await?.error_for_status()?;
+        let final_url = response.url().to_string();
+        let body = response.text().await?;
+
+        if self.is_logged_in(&final_url, &body)? {
+            info!("Login succeeded, landed on {}", final_url);
+            Ok(())
+        } else {
+            Err(format!("Login to {} failed, landed on {}", self.login_url, final_url).into())
+        }
+    }
+
+    /// 判断页面是否为登出状态（命中 logged_out_selector）
+    pub fn is_logged_out(&self, body: &str) -> bool {
+        match &self.logged_out_selector {
+            Some(selector) => matches_selector(body, selector).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    fn is_logged_in(&self, final_url: &str, body: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        if let Some(prefix) = &self.success_redirect {
+            if final_url.starts_with(prefix.as_str()) {
+                return Ok(true);
+            }
+        }
+        if let Some(selector) = &self.success_selector {
+            if matches_selector(body, selector)? {
+                return Ok(true);
+            }
+        }
+        // 未配置任何校验条件时，只要没有命中登出标记就认为成功
+        Ok(self.success_redirect.is_none() && self.success_selector.is_none() && !self.is_logged_out(body))
+    }
+}
+
+/// 主机匹配，支持 `*.example.com` 形式的子域名通配
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => {
+            let (domain, host) = (domain.to_ascii_lowercase(), host.to_ascii_lowercase());
+            host == domain || host.ends_with(&format!(".{}", domain))
+        }
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
+fn parse_selector(selector: &str) -> Result<Selector, Box<dyn Error + Send + Sync>> {
+    Selector::parse(selector).map_err(|e| format!("Invalid selector {}: {:?}", selector, e).into())
+}
+
+fn matches_selector(body: &str, selector: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let selector = parse_selector(selector)?;
+    let document = Html::parse_document(body);
+    let matched = document.select(&selector).next().is_some();
+    Ok(matched)
+}
+
+// 返回匹配的 input 元素的 name 属性和 token
+fn extract_csrf_token(body: &str, selector: &str) -> Result<(Option<String>, String), Box<dyn Error + Send + Sync>> {
+    let parsed = parse_selector(selector)?;
+    let document = Html::parse_document(body);
+    document
+        .select(&parsed)
+        .find_map(|element| {
+            let value = element.value();
+            let token = value.attr("value").or_else(|| value.attr("content"))?;
+            let name = value.attr("name").filter(|_| value.name() == "input");
+            Some((name.map(|name| name.to_string()), token.to_string()))
+        })
+        .ok_or_else(|| format!("CSRF token not found by selector {}", selector).into())
+}
@@ -0,0 +1,147 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use chrono::Utc;
+use crate::utils::atomic_write;
+
+/// HTTP 缓存配置，对应任务配置文件中的 `[cache]` 段
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CacheConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig { enabled: default_enabled() }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 缓存条目的元信息，保存校验字段（ETag / Last-Modified）以及原始响应的状态码和内容类型
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CacheEntry {
+    pub url: String,
+    /// 旧版本的缓存没有记录状态码，这些条目视为未命中
+    #[serde(default)]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub content_type: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: String,
+}
+
+/// 本次运行中页面相对于缓存的变化情况
+pub enum CacheOutcome {
+    /// 服务端返回 304，复用缓存内容
+    NotModified,
+    /// 服务端返回完整内容，但与缓存中的内容相同
+    Unchanged,
+    /// 缓存中已有该页面，但内容已更新
+    Changed,
+    /// 缓存中没有该页面
+    New,
+}
+
+/// 本次运行的缓存统计
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CacheStats {
+    /// 未变化的页面数，包括服务端返回 304 和返回的内容与缓存相同的页面
+    pub unchanged: u32,
+    /// 未变化的页面中服务端返回 304 的页面数
+    #[serde(default)]
+    pub not_modified: u32,
+    pub changed: u32,
+    pub new: u32,
+}
+
+/// 按任务保存在磁盘上的 HTTP 缓存，每个 URL 对应 `<hash>.json` 和 `<hash>.body` 两个文件
+pub struct HttpCache {
+    dir: PathBuf,
+    not_modified: AtomicU32,
+    unchanged: AtomicU32,
+    changed: AtomicU32,
+    new: AtomicU32,
+}
+
+impl HttpCache {
+    pub fn new(dir: &str) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(HttpCache {
+            dir: PathBuf::from(dir),
+            not_modified: AtomicU32::new(0),
+            unchanged: AtomicU32::new(0),
+            changed: AtomicU32::new(0),
+            new: AtomicU32::new(0),
+        })
+    }
+
+    /// 读取 URL 对应的缓存元信息，缓存内容缺失时视为未命中
+    pub fn lookup(&self, url: &str) -> Option<CacheEntry> {
+        let (meta_path, body_path) = self.paths(url);
+        if !body_path.exists() {
+            return None;
+        }
+        let file = File::open(meta_path).ok()?;
+        serde_json::from_reader(BufReader::new(file)).ok().filter(|entry: &CacheEntry| entry.status.is_some())
+    }
+
+    /// 读取缓存的页面内容
+    pub fn load_body(&self, url: &str) -> std::io::Result<String> {
+        let (_, body_path) = self.paths(url);
+        fs::read_to_string(body_path)
+    }
+
+    /// 保存成功响应的页面内容、状态码、内容类型及校验字段
+    pub fn store(&self, url: &str, status: u16, content_type: &str, etag: Option<String>, last_modified: Option<String>, body: &str) -> std::io::Result<()> {
+        let (meta_path, body_path) = self.paths(url);
+        let entry = CacheEntry {
+            url: url.to_string(),
+            status: Some(status),
+            content_type: content_type.to_string(),
+            etag,
+            last_modified,
+            fetched_at: Utc::now().to_rfc3339(),
+        };
+        atomic_write(&body_path, body.as_bytes())?;
+        atomic_write(&meta_path, &serde_json::to_vec(&entry)?)?;
+        Ok(())
+    }
+
+    pub fn record(&self, outcome: CacheOutcome) {
+        let counter = match outcome {
+            CacheOutcome::NotModified => {
+                self.not_modified.fetch_add(1, Ordering::SeqCst);
+                &self.unchanged
+            }
+            CacheOutcome::Unchanged => &self.unchanged,
+            CacheOutcome::Changed => &self.changed,
+            CacheOutcome::New => &self.new,
+        };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            unchanged: self.unchanged.load(Ordering::SeqCst),
+            not_modified: self.not_modified.load(Ordering::SeqCst),
+            changed: self.changed.load(Ordering::SeqCst),
+            new: self.new.load(Ordering::SeqCst),
+        }
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        (self.dir.join(format!("{}.json", key)), self.dir.join(format!("{}.body", key)))
+    }
+}
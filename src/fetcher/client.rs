@@ -1,39 +1,206 @@
-use reqwest::blocking::{Client, RequestBuilder};
-use reqwest::Proxy;
-use reqwest::header::USER_AGENT;
-// 引入宏
-use crate::info;
-use crate::warn;
-use crate::error;
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::header::{HeaderName, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, Proxy, RequestBuilder, Response, StatusCode, Url};
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+use super::auth::AuthConfig;
+use super::cache::{CacheOutcome, CacheStats, HttpCache};
+use super::limits::{DownloadConfig, DownloadStats, OversizeAction};
+// 日志宏
+use log::{debug, info, warn};
+
+lazy_static! {
+    /// 所有抓取共用的 tokio 运行时，阻塞接口和异步引擎都在其上执行请求
+    pub static ref RUNTIME: Runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime");
+}
+
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
 
 pub struct HttpClient {
     client: Client,
+    auth: AuthConfig,
+    // 登录代数，每成功登录一次加一，用于避免多个线程同时重复登录
+    login_generation: Mutex<u64>,
+    cache: Option<HttpCache>,
+    download: DownloadConfig,
+    download_stats: DownloadStats,
+    // 本次运行中重试的请求数
+    retries: AtomicU32,
+    // 单个请求的超时时间，为空时不限制
+    timeout: Option<Duration>,
+}
+
+/// 抓取到的页面
+pub struct Page {
+    /// 跟随重定向后的最终 URL
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+/// 一次抓取的结果
+pub enum Fetched {
+    Page(Page),
+    /// 按下载限制跳过，附带跳过原因
+    Skipped(String),
+}
+
+// 单次请求的结果
+enum FetchOutcome {
+    Page(Page),
+    // 页面命中了登出标记，此时不写入缓存
+    LoggedOut,
+    // 按下载限制跳过
+    Skipped(String),
 }
 
 impl HttpClient {
-    pub fn new(proxy_url: Option<&str>) -> Self {
-        let client = if let Some(proxy_url) = proxy_url {
-            let proxy = Proxy::all(proxy_url).unwrap();
-            Client::builder()
-                .proxy(proxy)
-                .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-                .build()
-                .unwrap()
-        } else {
-            Client::builder()
-                .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-                .build()
-                .unwrap()
+    pub fn new(proxy_url: Option<&str>, auth: AuthConfig) -> Self {
+        let mut builder = Client::builder()
+            .user_agent(DEFAULT_USER_AGENT)
+            .cookie_store(true);
+        if let Some(proxy_url) = proxy_url {
+            builder = builder.proxy(Proxy::all(proxy_url).unwrap());
+        }
+        let client = builder.build().unwrap();
+
+        HttpClient {
+            client,
+            auth,
+            login_generation: Mutex::new(0),
+            cache: None,
+            download: DownloadConfig::default(),
+            download_stats: DownloadStats::default(),
+            retries: AtomicU32::new(0),
+            timeout: None,
+        }
+    }
+
+    /// 启用磁盘缓存，后续请求会携带 If-None-Match / If-Modified-Since
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// 设置响应体大小限制以及跳过的内容类型、扩展名
+    pub fn with_download_config(mut self, download: DownloadConfig) -> Self {
+        self.download = download;
+        self
+    }
+
+    /// 设置单个请求（包括读取响应体）的超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 本次运行的缓存统计，未启用缓存时返回 None
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// 本次运行中被跳过和被截断的页面计数
+    pub fn download_stats(&self) -> &DownloadStats {
+        &self.download_stats
+    }
+
+    /// 本次运行中重试的请求数
+    pub fn retries(&self) -> u32 {
+        self.retries.load(Ordering::SeqCst)
+    }
+
+    /// 执行表单登录（未配置时直接返回），需在开始抓取前调用
+    pub fn login(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        RUNTIME.block_on(self.login_async())
+    }
+
+    pub async fn login_async(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(form_login) = &self.auth.form_login else {
+            return Ok(());
         };
+        let mut generation = self.login_generation.lock().await;
+        form_login.login(&self.client, &self.auth, self.timeout).await?;
+        *generation += 1;
+        Ok(())
+    }
 
-        HttpClient { client }
+    /// 抓取页面，页面可能按下载限制被跳过
+    pub async fn get_async(&self, url: &str) -> Result<Fetched, Box<dyn Error + Send + Sync>> {
+        let generation = *self.login_generation.lock().await;
+        let outcome = match self.fetch(url).await? {
+            FetchOutcome::LoggedOut => {
+                // 检测到登出标记时重新登录并重试一次
+                warn!(url = url; "Logged out while fetching {}, logging in again", url);
+                self.relogin(generation).await?;
+                self.retries.fetch_add(1, Ordering::SeqCst);
+                self.fetch(url).await?
+            }
+            outcome => outcome,
+        };
+
+        match outcome {
+            FetchOutcome::Page(page) => Ok(Fetched::Page(page)),
+            FetchOutcome::LoggedOut => Err(format!("Still logged out after login while fetching {}", url).into()),
+            FetchOutcome::Skipped(reason) => {
+                info!(url = url; "Skipped {}: {}", url, reason);
+                Ok(Fetched::Skipped(reason))
+            }
+        }
     }
 
-    pub fn get(&self, url: &str) -> Result<String, reqwest::Error> {
-        let response = self.client.get(url).send()?;
+    /// 检查链接是否有效，返回状态码；先发送 HEAD 请求，返回错误状态时再用 GET 确认，不读取响应体
+    pub async fn check_async(&self, url: &str) -> Result<u16, String> {
+        let parsed_url = Url::parse(url).map_err(|e| e.to_string())?;
+        let head = self.auth.apply(self.client.head(parsed_url.clone()), &parsed_url);
+        let status = self.send(head).await.map_err(|e| describe_error(&e))?.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(status.as_u16());
+        }
+        // 部分服务器不支持 HEAD 请求，或对 HEAD 和 GET 的处理不同
+        debug!(url = url; "HEAD {} returned {}, retrying with GET", url, status);
+        let get = self.auth.apply(self.client.get(parsed_url.clone()), &parsed_url);
+        let response = self.send(get).await.map_err(|e| describe_error(&e))?;
+        Ok(response.status().as_u16())
+    }
+
+    // 发送请求，设置了超时时间时应用到该请求
+    async fn send(&self, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout).send().await,
+            None => request.send().await,
+        }
+    }
+
+    async fn fetch(&self, url: &str) -> Result<FetchOutcome, Box<dyn Error + Send + Sync>> {
+        let parsed_url = Url::parse(url)?;
+        if self.download.skip_url(&parsed_url) {
+            return Ok(FetchOutcome::Skipped("extension in skip list".to_string()));
+        }
+
+        let mut request = self.auth.apply(self.client.get(parsed_url.clone()), &parsed_url);
+
+        // 带上缓存中的校验字段发起条件请求
+        let cached = self.cache.as_ref().and_then(|cache| cache.lookup(url));
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = self.send(request).await.map_err(|e| describe_error(&e))?;
         // 打印最终的 URL 和状态码
-        info!("Final URL: {}", response.url());
-        info!("Status: {}", response.status());
+        debug!(url = url; "Final URL: {}", response.url());
+        debug!(url = url; "Status: {}", response.status());
 
         // 检查响应头中的 Content-Encoding
         let content_encoding = response
@@ -42,9 +209,120 @@ impl HttpClient {
             .and_then(|v| v.to_str().ok())
             .unwrap_or("");
 
-        info!("Content-Encoding: {}", content_encoding);
+        debug!(url = url; "Content-Encoding: {}", content_encoding);
+
+        let final_url = response.url().to_string();
+        let status = response.status().as_u16();
+        let content_type = header_value(&response, CONTENT_TYPE).unwrap_or_default();
+
+        // 304 说明页面未变化，直接复用缓存内容，状态码和内容类型取缓存的原始响应
+        if let (Some(cache), Some(entry), StatusCode::NOT_MODIFIED) = (&self.cache, &cached, response.status()) {
+            cache.record(CacheOutcome::NotModified);
+            let body = cache.load_body(url)?;
+            let status = entry.status.unwrap_or(status);
+            return Ok(FetchOutcome::Page(Page { final_url, status, content_type: entry.content_type.clone(), body }));
+        }
 
-        let body = response.text_with_charset("UTF-8")?;
-        Ok(body)
+        if self.download.skip_content_type(&content_type) {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            return Ok(FetchOutcome::Skipped(format!("content type {}", mime)));
+        }
+        // 在读取响应体之前根据 Content-Length 预判是否超限
+        if self.download.on_oversize == OversizeAction::Abort {
+            if let Some(length) = response.content_length().filter(|l| *l > self.download.max_body_size) {
+                info!(url = url; "Content-Length {} of {} exceeds limit", length, url);
+                return Ok(FetchOutcome::Skipped("body exceeds size limit".to_string()));
+            }
+        }
+
+        let etag = header_value(&response, ETAG);
+        let last_modified = header_value(&response, LAST_MODIFIED);
+        let Some(body) = self.read_body(response, &content_type).await? else {
+            return Ok(FetchOutcome::Skipped("body exceeds size limit".to_string()));
+        };
+
+        if self.auth.form_login.as_ref().is_some_and(|form_login| form_login.is_logged_out(&body)) {
+            return Ok(FetchOutcome::LoggedOut);
+        }
+
+        // 只缓存成功的响应，错误页面下次照常请求
+        let Some(cache) = self.cache.as_ref().filter(|_| (200..300).contains(&status)) else {
+            return Ok(FetchOutcome::Page(Page { final_url, status, content_type, body }));
+        };
+
+        // 服务端不支持条件请求时，通过比较内容判断是否变化
+        let outcome = match &cached {
+            Some(_) if cache.load_body(url).map(|old| old == body).unwrap_or(false) => CacheOutcome::Unchanged,
+            Some(_) => CacheOutcome::Changed,
+            None => CacheOutcome::New,
+        };
+        cache.record(outcome);
+        if let Err(e) = cache.store(url, status, &content_type, etag, last_modified, &body) {
+            warn!(url = url; "Failed to cache {}: {}", url, e);
+        }
+
+        Ok(FetchOutcome::Page(Page { final_url, status, content_type, body }))
+    }
+
+    // 流式读取响应体，最多读取 max_body_size 字节；超限且配置为放弃时返回 None
+    async fn read_body(&self, mut response: Response, content_type: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        let max_body_size = self.download.max_body_size;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| describe_error(&e))? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() as u64 > max_body_size {
+                break;
+            }
+        }
+
+        if bytes.len() as u64 > max_body_size {
+            if self.download.on_oversize == OversizeAction::Abort {
+                return Ok(None);
+            }
+            bytes.truncate(max_body_size as usize);
+            self.download_stats.record_truncated();
+        }
+
+        // 按 Content-Type 中声明的字符集解码，缺省为 UTF-8
+        let encoding = content_type
+            .split(';')
+            .find_map(|part| part.trim().strip_prefix("charset="))
+            .and_then(|label| Encoding::for_label(label.trim_matches('"').as_bytes()))
+            .unwrap_or(UTF_8);
+        let (body, _, _) = encoding.decode(&bytes);
+        Ok(Some(body.into_owned()))
+    }
+
+    // 只有登录代数与请求发出前一致时才真正登录，其他线程已完成重新登录则直接返回
+    async fn relogin(&self, seen_generation: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut generation = self.login_generation.lock().await;
+        if *generation != seen_generation {
+            return Ok(());
+        }
+        if let Some(form_login) = &self.auth.form_login {
+            form_login.login(&self.client, &self.auth, self.timeout).await?;
+            *generation += 1;
+        }
+        Ok(())
     }
 }
+
+/// 把请求错误归类为超时或连接失败（包括 DNS 解析失败），附带最底层的错误原因
+pub fn describe_error(e: &reqwest::Error) -> String {
+    if e.is_timeout() {
+        return "timeout".to_string();
+    }
+    let mut cause: &dyn Error = e;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    if e.is_connect() {
+        format!("connection failed: {}", cause)
+    } else {
+        cause.to_string()
+    }
+}
+
+fn header_value(response: &Response, name: HeaderName) -> Option<String> {
+    response.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+}
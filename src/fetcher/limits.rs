@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use serde::{Serialize, Deserialize};
+use reqwest::Url;
+
+/// 下载限制配置，对应任务配置文件中的 `[download]` 段
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadConfig {
+    /// 响应体最大字节数
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: u64,
+    /// 超过最大字节数时的处理方式
+    #[serde(default)]
+    pub on_oversize: OversizeAction,
+    /// 跳过的 Content-Type 前缀，如 `image/`
+    #[serde(default = "default_skip_content_types")]
+    pub skip_content_types: Vec<String>,
+    /// 跳过的 URL 扩展名（不含点），在发起请求前检查
+    #[serde(default = "default_skip_extensions")]
+    pub skip_extensions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizeAction {
+    /// 放弃该页面
+    #[default]
+    Abort,
+    /// 只保留前 max_body_size 字节
+    Truncate,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        DownloadConfig {
+            max_body_size: default_max_body_size(),
+            on_oversize: OversizeAction::default(),
+            skip_content_types: default_skip_content_types(),
+            skip_extensions: default_skip_extensions(),
+        }
+    }
+}
+
+fn default_max_body_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_skip_content_types() -> Vec<String> {
+    ["image/", "video/", "audio/", "font/", "application/zip", "application/gzip", "application/x-tar",
+        "application/x-7z-compressed", "application/x-rar-compressed", "application/octet-stream", "application/pdf"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_skip_extensions() -> Vec<String> {
+    ["jpg", "jpeg", "png", "gif", "webp", "svg", "ico", "bmp", "mp4", "avi", "mov", "mkv", "webm", "mp3", "wav",
+        "flac", "zip", "gz", "tgz", "tar", "7z", "rar", "bz2", "xz", "iso", "exe", "dmg", "pdf", "woff", "woff2", "ttf"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+impl DownloadConfig {
+    /// 根据 URL 扩展名判断是否跳过
+    pub fn skip_url(&self, url: &Url) -> bool {
+        let path = url.path().to_ascii_lowercase();
+        match path.rsplit('/').next().and_then(|name| name.rsplit_once('.')) {
+            Some((_, ext)) => self.skip_extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)),
+            None => false,
+        }
+    }
+
+    /// 根据响应头中的 Content-Type 判断是否跳过
+    pub fn skip_content_type(&self, content_type: &str) -> bool {
+        let content_type = content_type.to_ascii_lowercase();
+        self.skip_content_types.iter().any(|t| content_type.starts_with(&t.to_ascii_lowercase()))
+    }
+}
+
+/// 本次运行中被截断的页面计数，被跳过的页面由任务进度统计
+#[derive(Default)]
+pub struct DownloadStats {
+    truncated: AtomicU32,
+}
+
+impl DownloadStats {
+    pub fn record_truncated(&self) {
+        self.truncated.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn truncated(&self) -> u32 {
+        self.truncated.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skip(url: &str) -> bool {
+        DownloadConfig::default().skip_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn skips_by_extension() {
+        assert!(skip("http://a.com/photo.jpg"));
+        assert!(skip("http://a.com/files/archive.tar.gz"));
+        assert!(skip("http://a.com/PHOTO.JPG"));
+        assert!(skip("http://a.com/report.pdf?download=1"));
+    }
+
+    #[test]
+    fn keeps_pages() {
+        assert!(!skip("http://a.com/"));
+        assert!(!skip("http://a.com/index.html"));
+        assert!(!skip("http://a.com/docs/v1.2/intro"));
+        assert!(!skip("http://a.com/page?file=photo.jpg"));
+    }
+}
@@ -1,3 +1,8 @@
+pub mod auth;
+pub mod cache;
 pub mod client;
+pub mod limits;
 
-pub use reqwest::Proxy;
+pub use self::auth::AuthConfig;
+pub use self::cache::{CacheConfig, CacheStats, HttpCache};
+pub use self::limits::DownloadConfig;
@@ -1,19 +1,29 @@
-
 #[macro_use]
 extern crate lazy_static;
 
 use std::fs;
-use std::process::Command;
+use chrono::Local;
+use std::net::SocketAddr;
 use std::process::exit;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use crate::worker::{TaskManager, TaskStatus};
-use crate::utils::parse_args;
-use crate::utils::logging::Logging;
-use ctrlc;
+use crate::worker::control;
+use crate::utils::{budget_overrides, graph_formats, list_options, parse_args, task_ref, TaskConfig};
+use crate::utils::logging::{self, LogOptions};
+use crate::utils::output::{self, OutputMode};
 
 static TERMINATE: AtomicBool = AtomicBool::new(false);
 
+// 进程退出码，供 cron / CI 判断运行结果；命令行参数错误时 clap 以 2 退出
+const EXIT_FAILURE: i32 = 1;
+// 任务完成但有 URL 抓取失败，或死链检查发现了死链
+const EXIT_PARTIAL: i32 = 3;
+// 配置文件或种子文件无法读取
+const EXIT_CONFIG: i32 = 78;
+// 任务被 Ctrl+C 或 SIGTERM 中止
+const EXIT_ABORTED: i32 = 130;
+
 lazy_static! {
     pub static ref MULTI_PROGRESS: Arc<MultiProgress> = Arc::new(MultiProgress::new());
 }
@@ -22,62 +32,250 @@ lazy_static! {
 mod worker;
 mod fetcher;
 mod parser;
-mod writer;
+mod store;
 mod utils;
+mod bench;
+mod server;
 
 fn main() {
 
-    // 初始化 logger
-    let _logger = Logging::new(&MULTI_PROGRESS);
-
     let m = MULTI_PROGRESS.clone();
 
     let (matches, mut app) = parse_args();
 
+    // 非交互式终端和仪表盘模式下不绘制进度条；服务模式下同时运行多个任务，只输出状态行
+    let serving = matches.subcommand_name() == Some("serve");
+    let mode = output::init(
+        matches.is_present("quiet"),
+        matches.is_present("no-progress") || serving,
+        matches.is_present("tui") && !serving,
+    );
+    if mode != OutputMode::Interactive {
+        m.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    // 初始化 logger
+    logging::init(&m, LogOptions {
+        level: matches.get_one::<String>("log-level").cloned(),
+        json: matches.is_present("log-json"),
+        mode,
+    });
+
     // let task_manager = Arc::new(Mutex::new(TaskManager::new()));
     let mut task_manager = TaskManager::new();
 
-    // 设置 Ctrl+C 处理
-    ctrlc::set_handler(move || {
-        println!("Task state saved. Exiting...");
-        TERMINATE.store(true, Ordering::SeqCst);
-    }).expect("Error setting Ctrl+C handler");
-
     match matches.subcommand() {
-        Some(("create", sub_m)) => {
+        Some((command @ ("create" | "check-links"), sub_m)) => {
             let file = sub_m.get_one::<String>("file").unwrap();
             let name = sub_m.get_one::<String>("name").unwrap();
             let deep = sub_m.get_one::<u32>("deep").unwrap();
+            let mut config = match sub_m.get_one::<String>("config") {
+                Some(path) => TaskConfig::load(path).unwrap_or_else(|e| {
+                    eprintln!("Could not read config file {}: {}", path, e);
+                    exit(EXIT_CONFIG);
+                }),
+                None => TaskConfig::default(),
+            };
+            config.budget.merge(&budget_overrides(sub_m));
+            if let Some(cron) = sub_m.get_one::<String>("schedule") {
+                config.schedule.cron = Some(cron.clone());
+            }
+            if command == "check-links" {
+                config.check_links.enabled = true;
+                if let Some(timeout) = sub_m.get_one::<u64>("timeout") {
+                    config.check_links.timeout_secs = *timeout;
+                }
+            }
+            if let Err(e) = config.validate() {
+                eprintln!("{}", e);
+                exit(EXIT_CONFIG);
+            }
             // let mut task_manager = task_manager.lock().unwrap();
-            let start_urls = fs::read_to_string(file).expect("Could not read file")
+            let start_urls = fs::read_to_string(file)
+                .unwrap_or_else(|e| {
+                    eprintln!("Could not read file {}: {}", file, e);
+                    exit(EXIT_CONFIG);
+                })
                 .lines()
                 .map(|line| line.to_string())
                 .collect();
-            println!("Creating and starting new task: {}", name);
-            task_manager.create_task(start_urls, name.to_string(), *deep);
-            task_manager.run(&m);
-            println!("All threads finished!");
-            task_manager.save_state(&format!("tasks/{}/visited_urls.txt", name)).unwrap();
+            notice(&format!("Creating and starting new task: {}", name));
+            if let Err(e) = task_manager.create_task(start_urls, name.to_string(), *deep, config, sub_m.is_present("force")) {
+                eprintln!("{}", e);
+                exit(EXIT_FAILURE);
+            }
+            run_task(&mut task_manager, &m, matches.get_one::<SocketAddr>("metrics-addr").copied());
         }
-        Some(("list", _sub_m)) => {
+        Some(("list", sub_m)) => {
             // println!("Listing all tasks...");
             // let mut task_manager = task_manager.lock().unwrap();
-            task_manager.list_tasks();  // Call the list_tasks method
+            task_manager.list_tasks(&list_options(sub_m));  // Call the list_tasks method
+        }
+        Some(("show", sub_m)) => {
+            let task = task_ref(sub_m);
+            if !task_manager.show_task(&task, sub_m.is_present("json")) {
+                eprintln!("Task {} not found", task);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Some(("pause", sub_m)) => {
+            if let Err(e) = task_manager.pause_task(&task_ref(sub_m)) {
+                eprintln!("{}", e);
+                exit(EXIT_FAILURE);
+            }
         }
         Some(("resume", sub_m)) => {
-            let task_id = sub_m.get_one::<u32>("id").unwrap(); // task_id is of type &u32
-            println!("Resuming task with ID: {}", *task_id);   // Dereference task_id to get u32、
-            // Implement task resume logic here
+            let task = task_ref(sub_m);
+            // 暂停中的任务由运行它的进程继续抓取
+            if task_manager.unpause_task(&task) {
+                return;
+            }
+            notice(&format!("Resuming task: {}", task));
+            if !task_manager.recover(&task) {
+                eprintln!("Task {} not found", task);
+                exit(EXIT_FAILURE);
+            }
+            task_manager.update_budget(&budget_overrides(sub_m));
+            run_task(&mut task_manager, &m, matches.get_one::<SocketAddr>("metrics-addr").copied());
         }
         Some(("restart", sub_m)) => {
-            let task_id = sub_m.get_one::<u32>("id").unwrap();
-            println!("Restarting task with ID: {}", *task_id);
-            // Implement task restart logic here
+            let task = task_ref(sub_m);
+            notice(&format!("Restarting task: {}", task));
+            if !task_manager.reset_task(&task) {
+                eprintln!("Task {} not found", task);
+                exit(EXIT_FAILURE);
+            }
+            task_manager.update_budget(&budget_overrides(sub_m));
+            run_task(&mut task_manager, &m, matches.get_one::<SocketAddr>("metrics-addr").copied());
+        }
+        Some(("schedule", sub_m)) => {
+            let task = task_ref(sub_m);
+            match task_manager.schedule_task(&task, sub_m.get_one::<String>("cron").cloned()) {
+                Ok(Some(next_run)) => println!("Task {} is scheduled, next run at {}", task, next_run.with_timezone(&Local).to_rfc3339()),
+                Ok(None) => println!("Task {} is not scheduled", task),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(EXIT_FAILURE);
+                }
+            }
+        }
+        Some(("diff", sub_m)) => {
+            let runs: Vec<u32> = sub_m.get_many::<u32>("run").map(|runs| runs.copied().collect()).unwrap_or_default();
+            match task_manager.diff_task(&task_ref(sub_m), &runs) {
+                Ok(diff) if sub_m.is_present("json") => println!("{}", serde_json::to_string_pretty(&diff).expect("Failed to serialize diff")),
+                Ok(diff) => diff.print(),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(EXIT_FAILURE);
+                }
+            }
+        }
+        Some(("export-graph", sub_m)) => {
+            let output = sub_m.get_one::<String>("output").map(String::as_str);
+            let top = *sub_m.get_one::<usize>("top").unwrap();
+            if let Err(e) = task_manager.export_graph(&task_ref(sub_m), &graph_formats(sub_m), output, top) {
+                eprintln!("{}", e);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Some(("delete", sub_m)) => {
+            if let Err(e) = task_manager.delete_task(&task_ref(sub_m), sub_m.is_present("yes")) {
+                eprintln!("{}", e);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Some(("archive", sub_m)) => {
+            let output = sub_m.get_one::<String>("output").map(String::as_str);
+            if let Err(e) = task_manager.archive_task(&task_ref(sub_m), output, sub_m.is_present("delete")) {
+                eprintln!("{}", e);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Some(("import", sub_m)) => {
+            let archive = sub_m.get_one::<String>("archive").unwrap();
+            let name = sub_m.get_one::<String>("name").map(String::as_str);
+            if let Err(e) = task_manager.import_task(archive, name, sub_m.is_present("force")) {
+                eprintln!("{}", e);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Some(("serve", sub_m)) => {
+            let addr = *sub_m.get_one::<SocketAddr>("addr").unwrap();
+            install_ctrlc_handler();
+            // SIGUSR1 / SIGUSR2 暂停或继续服务进程中运行的所有任务，而不是结束服务进程
+            control::install_signal_handlers();
+            if let Err(e) = server::serve(addr) {
+                eprintln!("Cannot serve API on {}: {}", addr, e);
+                exit(EXIT_FAILURE);
+            }
+        }
+        Some(("bench", sub_m)) => {
+            let pages = sub_m.get_one::<usize>("pages").unwrap();
+            let latency = sub_m.get_one::<u64>("latency").unwrap();
+            let concurrency = sub_m.get_one::<usize>("concurrency").unwrap();
+            bench::run(*pages, *latency, *concurrency);
         }
         _ => {
-            let _ = app.print_help().unwrap();
+            app.print_help().unwrap();
             println!();
         }
         // _ => unreachable!("Clap should ensure only valid subcommands are used"),
     }
 }
+
+// 运行任务并打印摘要，运行期间 Ctrl+C 或 SIGTERM 会停止任务并保存状态
+fn run_task(task_manager: &mut TaskManager, m: &Arc<MultiProgress>, metrics_addr: Option<SocketAddr>) {
+    install_ctrlc_handler();
+    // SIGUSR1 暂停任务，SIGUSR2 继续
+    control::install_signal_handlers();
+
+    if let Err(e) = task_manager.run(m, metrics_addr) {
+        eprintln!("{}", e);
+        exit(EXIT_FAILURE);
+    }
+    let task_info = task_manager.task_info();
+    let code = if TERMINATE.load(Ordering::SeqCst) {
+        EXIT_ABORTED
+    } else if task_info.status != TaskStatus::Finished {
+        EXIT_FAILURE
+    } else if task_info.failed > 0 || !task_manager.broken_links().is_empty() {
+        EXIT_PARTIAL
+    } else {
+        0
+    };
+
+    if output::output_mode() != OutputMode::Quiet {
+        if code == EXIT_ABORTED {
+            println!("Task state saved. Exiting...");
+        } else {
+            println!("All threads finished!");
+        }
+        task_manager.print_summary();
+    }
+    if code != 0 {
+        exit(code);
+    }
+}
+
+// 第一次收到 Ctrl+C 或 SIGTERM 时停止调度新的 URL，等待进行中的请求完成后保存状态；再次收到时立即退出
+fn install_ctrlc_handler() {
+    ctrlc::set_handler(move || {
+        if TERMINATE.swap(true, Ordering::SeqCst) {
+            force_exit();
+        }
+        eprintln!("Stopping, waiting for in-flight requests to finish (press Ctrl+C again to force exit)...");
+    }).expect("Error setting Ctrl+C handler");
+}
+
+/// 不再等待进行中的请求，立即退出；第二次 Ctrl+C 和仪表盘中第二次退出按键共用
+pub(crate) fn force_exit() -> ! {
+    eprintln!("Forced exit, in-flight URLs will be fetched again on resume");
+    exit(EXIT_ABORTED);
+}
+
+// 输出提示信息，安静模式下不输出
+fn notice(message: &str) {
+    if output::output_mode() != OutputMode::Quiet {
+        println!("{}", message);
+    }
+}
@@ -1,12 +1,23 @@
-use select::document::Document;
-use select::predicate::Name;
 use scraper::{Html, Selector};
 use readability::extractor;
-use regex::Regex;
 use std::error::Error;
 use std::io::Cursor;
 use reqwest::Url;
-use readability::extractor::Product;
+use sha2::{Digest, Sha256};
+
+/// 解析结果：页面中的链接、是否为内容页、标题、正文
+pub type ParsedContent = (Vec<Link>, bool, Option<String>, Option<String>);
+
+/// 页面中的一个链接
+#[derive(Clone, Debug)]
+pub struct Link {
+    /// href 属性的原始值，可能是相对地址
+    pub href: String,
+    /// 合并空白后的锚文本
+    pub text: String,
+    /// rel 属性中是否包含 nofollow
+    pub nofollow: bool,
+}
 
 #[derive(Clone)]
 pub struct Parser;
@@ -17,16 +28,22 @@ impl Parser {
     }
 
     /// 解析 HTML 内容，提取所有 URL，判断是否为内容页，并提取标题和正文内容
-    pub fn parse_content(&self, content: &str, url: &Url) -> Result<(Vec<String>, bool, Option<String>, Option<String>), Box<dyn Error>> {
+    pub fn parse_content(&self, content: &str, url: &Url) -> Result<ParsedContent, Box<dyn Error>> {
         // 使用scraper解析HTML
         let document = Html::parse_document(content);
 
-        // 用于匹配<a>标签并提取href属性
+        // 用于匹配<a>标签并提取href属性、锚文本和 rel=nofollow
         let selector = Selector::parse("a").unwrap();
         let mut urls = Vec::new();
         for element in document.select(&selector) {
             if let Some(href) = element.value().attr("href") {
-                urls.push(href.to_string());
+                let nofollow = element.value().attr("rel")
+                    .is_some_and(|rel| rel.split_whitespace().any(|value| value.eq_ignore_ascii_case("nofollow")));
+                urls.push(Link {
+                    href: href.to_string(),
+                    text: element.text().flat_map(str::split_whitespace).collect::<Vec<_>>().join(" "),
+                    nofollow,
+                });
             }
         }
 
@@ -44,3 +61,20 @@ impl Parser {
         Ok((urls, is_content_page, Some(title), Some(text_content)))
     }
 }
+
+/// 页面内容的 SHA-256 摘要（十六进制），用于判断两次运行之间页面是否变化
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 规范化正文：合并每行中的空白并去掉空行，避免排版变化被当成内容修改
+pub fn normalize_text(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
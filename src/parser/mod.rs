@@ -1,3 +1,4 @@
 pub mod html_parser;
+pub mod simhash;
 
-pub use self::html_parser::Parser;
+pub use self::html_parser::{content_hash, normalize_text, Link, Parser};
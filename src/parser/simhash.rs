@@ -0,0 +1,72 @@
+// 每个特征由相邻的几个词组成
+const SHINGLE_WORDS: usize = 3;
+
+/// 正文的 64 位 SimHash 指纹：按相邻三个词的组合计算，内容相近的页面只有少数几位不同。
+/// 少于 `min_words` 个词的正文返回 None
+pub fn simhash(text: &str, min_words: usize) -> Option<u64> {
+    let words: Vec<String> = text.split_whitespace().map(|word| word.to_lowercase()).collect();
+    if words.len() < min_words.max(SHINGLE_WORDS) {
+        return None;
+    }
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_WORDS) {
+        let hash = fnv1a(&shingle.join(" "));
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    Some(weights.iter().enumerate().filter(|(_, weight)| **weight > 0).fold(0, |hash, (bit, _)| hash | 1 << bit))
+}
+
+/// 两个指纹的相似度，即相同位数的比例（0 到 1）
+pub fn similarity(a: u64, b: u64) -> f64 {
+    1.0 - (a ^ b).count_ones() as f64 / 64.0
+}
+
+// 64 位 FNV-1a 哈希，结果不随 Rust 版本变化，指纹可以保存在任务存储中跨运行比较
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(prefix: &str, count: usize) -> String {
+        (0..count).map(|i| format!("{}{}", prefix, i)).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn short_text_has_no_fingerprint() {
+        assert_eq!(simhash("one two", 0), None);
+        assert_eq!(simhash(&words("w", 49), 50), None);
+        assert!(simhash(&words("w", 50), 50).is_some());
+    }
+
+    #[test]
+    fn ignores_case_and_whitespace() {
+        let text = words("w", 100);
+        assert_eq!(simhash(&text, 50), simhash(&text.to_uppercase().replace(' ', "\n  "), 50));
+    }
+
+    #[test]
+    fn near_duplicates_are_above_the_default_threshold() {
+        let text = words("w", 200);
+        let edited = text.replacen("w100", "changed", 1);
+        let similarity = similarity(simhash(&text, 50).unwrap(), simhash(&edited, 50).unwrap());
+        assert!(similarity >= 0.9, "similarity {}", similarity);
+    }
+
+    #[test]
+    fn different_pages_are_below_the_default_threshold() {
+        let similarity = similarity(simhash(&words("a", 200), 50).unwrap(), simhash(&words("b", 200), 50).unwrap());
+        assert!(similarity < 0.9, "similarity {}", similarity);
+    }
+
+    #[test]
+    fn similarity_counts_equal_bits() {
+        assert_eq!(similarity(0, 0), 1.0);
+        assert_eq!(similarity(0, u64::MAX), 0.0);
+        assert_eq!(similarity(0, 0xffff_ffff), 0.5);
+    }
+}
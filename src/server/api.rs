@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use chrono::Local;
+use serde::Deserialize;
+use serde_json::json;
+use serde::de::DeserializeOwned;
+use tokio::net::{TcpListener, TcpStream};
+use crate::TERMINATE;
+use crate::fetcher::client::RUNTIME;
+use crate::store::{validate_task_name, TaskRef};
+use crate::utils::TaskConfig;
+use crate::utils::cli::parse_since;
+use crate::worker::{BudgetConfig, ListOptions, ListSort, OutputFormat, ScheduleConfig, TaskInfo, TaskManager, TaskStatus};
+use crate::worker::control::PAUSE_POLL;
+use crate::worker::run_lock::RunLock;
+use super::http::{self, Request, Response};
+use super::Supervisor;
+use super::supervisor::SCHEDULE_POLL;
+// 日志宏
+use log::{info, warn};
+
+/// `POST /tasks` 的请求体，对应 create 子命令的参数
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CreateRequest {
+    name: String,
+    urls: Vec<String>,
+    deep: u32,
+    config: TaskConfig,
+    force: bool,
+}
+
+/// 在 `addr` 上提供任务管理 API 并按计划运行任务，直到进程收到终止信号；退出前停止所有任务并等待其保存状态
+pub fn serve(addr: SocketAddr) -> io::Result<()> {
+    let supervisor = Arc::new(Supervisor::new());
+    let listener = RUNTIME.block_on(TcpListener::bind(addr))?;
+    if !addr.ip().is_loopback() {
+        warn!("The API has no authentication, anyone who can reach {} can control tasks", addr);
+    }
+    println!("Serving task API on http://{}", listener.local_addr()?);
+
+    RUNTIME.block_on(async {
+        let mut schedule = tokio::time::interval(SCHEDULE_POLL);
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    if let Ok((stream, _)) = accepted {
+                        tokio::spawn(handle_connection(stream, Arc::clone(&supervisor)));
+                    }
+                }
+                _ = schedule.tick() => {
+                    let supervisor = Arc::clone(&supervisor);
+                    tokio::task::spawn_blocking(move || supervisor.start_due());
+                }
+                _ = tokio::time::sleep(PAUSE_POLL) => {
+                    if TERMINATE.load(Ordering::SeqCst) {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    println!("Stopping all tasks...");
+    supervisor.shutdown();
+    Ok(())
+}
+
+// 每个连接只处理一个请求；任务操作会访问数据库和文件，放到阻塞线程中执行
+async fn handle_connection(mut stream: TcpStream, supervisor: Arc<Supervisor>) {
+    let response = match http::read_request(&mut stream).await {
+        Ok(request) => {
+            let label = format!("{} /{}", request.method, request.path.join("/"));
+            let response = tokio::task::spawn_blocking(move || route(&supervisor, request))
+                .await
+                .unwrap_or_else(|e| Response::error(500, &format!("Request failed: {}", e)));
+            info!("{} {}", label, response.status);
+            response
+        }
+        Err(response) => response,
+    };
+    http::write_response(&mut stream, response).await;
+}
+
+fn route(supervisor: &Supervisor, request: Request) -> Response {
+    let path: Vec<&str> = request.path.iter().map(String::as_str).collect();
+    match (request.method.as_str(), path.as_slice()) {
+        ("GET", ["tasks"]) => list_tasks(supervisor, &request.query),
+        ("POST", ["tasks"]) => create_task(supervisor, &request.body),
+        ("GET", ["tasks", task]) => show_task(supervisor, task),
+        ("DELETE", ["tasks", task]) => delete_task(supervisor, task),
+        ("POST", ["tasks", task, "pause"]) => pause_task(supervisor, task),
+        ("POST", ["tasks", task, "resume"]) => resume_task(supervisor, task, &request.body),
+        ("POST", ["tasks", task, "restart"]) => restart_task(supervisor, task, &request.body),
+        ("POST", ["tasks", task, "stop"]) => stop_task(supervisor, task),
+        ("GET", ["tasks", task, "runs"]) => list_runs(supervisor, task),
+        ("PUT", ["tasks", task, "schedule"]) => schedule_task(supervisor, task, &request.body),
+        ("GET", ["tasks", task, "diff"]) => diff_task(supervisor, task, &request.query),
+        (_, ["tasks"] | ["tasks", _] | ["tasks", _, "pause" | "resume" | "restart" | "stop" | "runs" | "schedule" | "diff"]) => {
+            Response::error(405, "Method not allowed")
+        }
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+// GET /tasks?status=&since=&sort=&reverse=&limit=，与 list 子命令的选项相同
+fn list_tasks(supervisor: &Supervisor, query: &HashMap<String, String>) -> Response {
+    match list_options(query) {
+        Ok(options) => Response::json(200, &supervisor.task_manager().query_tasks(&options)),
+        Err(e) => Response::error(400, &e),
+    }
+}
+
+fn list_options(query: &HashMap<String, String>) -> Result<ListOptions, String> {
+    Ok(ListOptions {
+        status: query.get("status").map(|status| status.parse::<TaskStatus>()).transpose()?,
+        since: query.get("since").map(|since| parse_since(since)).transpose()?,
+        sort: match query.get("sort").map(String::as_str) {
+            None | Some("created") => ListSort::Created,
+            Some("name") => ListSort::Name,
+            Some("progress") => ListSort::Progress,
+            Some(sort) => return Err(format!("unknown sort field {}", sort)),
+        },
+        reverse: query.get("reverse").is_some_and(|reverse| reverse != "false" && reverse != "0"),
+        limit: query.get("limit").map(|limit| limit.parse().map_err(|_| format!("invalid limit {}", limit))).transpose()?,
+        format: OutputFormat::Json,
+    })
+}
+
+// POST /tasks：创建任务并立即开始运行
+fn create_task(supervisor: &Supervisor, body: &[u8]) -> Response {
+    let request: CreateRequest = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    // 任务名用作任务目录名
+    if let Err(e) = validate_task_name(&request.name) {
+        return Response::error(400, &e);
+    }
+    if request.urls.is_empty() {
+        return Response::error(400, "No URLs to crawl");
+    }
+    if request.deep == 0 {
+        return Response::error(400, "deep must be at least 1");
+    }
+    if let Err(e) = request.config.validate() {
+        return Response::error(400, &e);
+    }
+    if let Some(pid) = RunLock::holder(&format!("tasks/{}", request.name)) {
+        return Response::error(409, &format!("Task named {} is running in process {}", request.name, pid));
+    }
+
+    let mut task_manager = supervisor.task_manager();
+    if let Err(e) = task_manager.create_task(request.urls, request.name, request.deep, request.config, request.force) {
+        return Response::error(409, &e);
+    }
+    let task_info = task_manager.task_info().clone();
+    match supervisor.start(task_manager) {
+        Ok(()) => Response::json(201, &task_info),
+        Err(e) => Response::error(409, &e),
+    }
+}
+
+// GET /tasks/{task}：与 `show --json` 相同的报告
+fn show_task(supervisor: &Supervisor, task: &str) -> Response {
+    match supervisor.task_manager().task_report(&task_ref(task)) {
+        Some(report) => Response::json(200, &report),
+        None => Response::error(404, &format!("Task {} not found", task)),
+    }
+}
+
+// DELETE /tasks/{task}：删除任务记录和任务目录，运行中的任务不能删除
+fn delete_task(supervisor: &Supervisor, task: &str) -> Response {
+    let task_manager = supervisor.task_manager();
+    let task_info = match find_task(&task_manager, task) {
+        Ok(task_info) => task_info,
+        Err(response) => return response,
+    };
+    if let Err(response) = check_not_running(supervisor, &task_info) {
+        return response;
+    }
+    match task_manager.delete_task(&TaskRef::Id(task_info.id), true) {
+        Ok(()) => Response::empty(204),
+        Err(e) => Response::error(500, &e),
+    }
+}
+
+// POST /tasks/{task}/pause：暂停运行中的任务，也可以暂停其他进程中运行的任务
+fn pause_task(supervisor: &Supervisor, task: &str) -> Response {
+    let task_manager = supervisor.task_manager();
+    let task_info = match find_task(&task_manager, task) {
+        Ok(task_info) => task_info,
+        Err(response) => return response,
+    };
+    match task_manager.pause_task(&TaskRef::Id(task_info.id)) {
+        Ok(()) => Response::json(202, &task_info),
+        Err(e) => Response::error(409, &e),
+    }
+}
+
+// POST /tasks/{task}/resume：继续暂停中的任务，或在本进程中恢复已停止的任务；请求体可以包含新的抓取预算
+fn resume_task(supervisor: &Supervisor, task: &str, body: &[u8]) -> Response {
+    let mut task_manager = supervisor.task_manager();
+    let task = task_ref(task);
+    if task_manager.unpause_task(&task) {
+        return match task_manager.find_task(&task) {
+            Ok(task_info) => Response::json(202, &task_info),
+            Err(e) => Response::error(404, &e),
+        };
+    }
+    if let Ok(task_info) = task_manager.find_task(&task) {
+        if let Err(response) = check_not_running(supervisor, &task_info) {
+            return response;
+        }
+    }
+    let budget: BudgetConfig = match parse_body(body) {
+        Ok(budget) => budget,
+        Err(response) => return response,
+    };
+
+    // 任务存储中缺少该任务时从检查点恢复
+    if !task_manager.recover(&task) {
+        return Response::error(404, &format!("Task {} not found", task));
+    }
+    task_manager.update_budget(&budget);
+    start(supervisor, task_manager)
+}
+
+// POST /tasks/{task}/restart：清空进度后从种子 URL 重新运行
+fn restart_task(supervisor: &Supervisor, task: &str, body: &[u8]) -> Response {
+    let mut task_manager = supervisor.task_manager();
+    let task_info = match find_task(&task_manager, task) {
+        Ok(task_info) => task_info,
+        Err(response) => return response,
+    };
+    if let Err(response) = check_not_running(supervisor, &task_info) {
+        return response;
+    }
+    let budget: BudgetConfig = match parse_body(body) {
+        Ok(budget) => budget,
+        Err(response) => return response,
+    };
+
+    task_manager.reset_task(&TaskRef::Id(task_info.id));
+    task_manager.update_budget(&budget);
+    start(supervisor, task_manager)
+}
+
+// POST /tasks/{task}/stop：停止本进程中运行的任务，进行中的请求完成后保存状态
+fn stop_task(supervisor: &Supervisor, task: &str) -> Response {
+    let task_info = match find_task(&supervisor.task_manager(), task) {
+        Ok(task_info) => task_info,
+        Err(response) => return response,
+    };
+    if supervisor.stop(task_info.id) {
+        return Response::json(202, &task_info);
+    }
+    match RunLock::holder(&format!("tasks/{}", task_info.name)) {
+        Some(pid) => Response::error(409, &format!("Task {} ({}) is running in process {}, not in this server", task_info.id, task_info.name, pid)),
+        None => Response::error(409, &format!("Task {} ({}) is not running", task_info.id, task_info.name)),
+    }
+}
+
+// GET /tasks/{task}/runs：任务的全部运行记录，最新的在前
+fn list_runs(supervisor: &Supervisor, task: &str) -> Response {
+    match supervisor.task_manager().task_runs(&task_ref(task)) {
+        Ok(runs) => Response::json(200, &runs),
+        Err(e) => Response::error(404, &e),
+    }
+}
+
+// PUT /tasks/{task}/schedule：设置任务计划，请求体为 `{"cron": "0 3 * * *"}`，cron 为 null 或请求体为空时清除计划
+fn schedule_task(supervisor: &Supervisor, task: &str, body: &[u8]) -> Response {
+    let task_manager = supervisor.task_manager();
+    if let Err(response) = find_task(&task_manager, task) {
+        return response;
+    }
+    let schedule: ScheduleConfig = match parse_body(body) {
+        Ok(schedule) => schedule,
+        Err(response) => return response,
+    };
+    match task_manager.schedule_task(&task_ref(task), schedule.cron.clone()) {
+        Ok(next_run) => Response::json(200, &json!({ "cron": schedule.cron, "next_run": next_run.map(|next| next.with_timezone(&Local).to_rfc3339()) })),
+        Err(e) => Response::error(400, &e),
+    }
+}
+
+// GET /tasks/{task}/diff?from=&to=：两次运行之间的页面变化，都省略时比较最近两次运行，只给出 to 时与它之前的一次运行比较
+fn diff_task(supervisor: &Supervisor, task: &str, query: &HashMap<String, String>) -> Response {
+    let task_manager = supervisor.task_manager();
+    let task_info = match find_task(&task_manager, task) {
+        Ok(task_info) => task_info,
+        Err(response) => return response,
+    };
+    let mut runs = Vec::new();
+    for key in ["from", "to"] {
+        match query.get(key).map(|run| run.parse::<u32>()) {
+            Some(Ok(run)) => runs.push(run),
+            Some(Err(_)) => return Response::error(400, &format!("invalid run ID for {}", key)),
+            None => {}
+        }
+    }
+    if query.contains_key("from") && !query.contains_key("to") {
+        return Response::error(400, "from requires to");
+    }
+    match task_manager.diff_task(&TaskRef::Id(task_info.id), &runs) {
+        Ok(diff) => Response::json(200, &diff),
+        Err(e) => Response::error(400, &e),
+    }
+}
+
+// 在本进程中运行已加载的任务
+fn start(supervisor: &Supervisor, task_manager: TaskManager) -> Response {
+    let task_info = task_manager.task_info().clone();
+    match supervisor.start(task_manager) {
+        Ok(()) => Response::json(202, &task_info),
+        Err(e) => Response::error(409, &e),
+    }
+}
+
+// 路径中的任务可以是 ID 或任务名，与命令行的位置参数相同
+fn task_ref(task: &str) -> TaskRef {
+    TaskRef::IdOrName(task.to_string())
+}
+
+fn find_task(task_manager: &TaskManager, task: &str) -> Result<TaskInfo, Response> {
+    task_manager.find_task(&task_ref(task)).map_err(|e| Response::error(404, &e))
+}
+
+// 任务在本进程或其他进程中运行时返回 409
+fn check_not_running(supervisor: &Supervisor, task_info: &TaskInfo) -> Result<(), Response> {
+    if supervisor.is_running(task_info.id) {
+        return Err(Response::error(409, &format!("Task {} ({}) is already running", task_info.id, task_info.name)));
+    }
+    match RunLock::holder(&format!("tasks/{}", task_info.name)) {
+        Some(pid) => Err(Response::error(409, &format!("Task {} ({}) is running in process {}", task_info.id, task_info.name, pid))),
+        None => Ok(()),
+    }
+}
+
+// 解析 JSON 请求体，请求体为空时使用默认值
+fn parse_body<T: DeserializeOwned + Default>(body: &[u8]) -> Result<T, Response> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(body).map_err(|e| Response::error(400, &format!("Invalid request body: {}", e)))
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// 请求体的最大字节数
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// 一个 HTTP 请求，路径中的各段已经过百分号解码
+pub struct Request {
+    pub method: String,
+    pub path: Vec<String>,
+    pub query: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// 一个 HTTP 响应
+pub struct Response {
+    pub status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// 以 JSON 格式返回 `value`
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        let mut body = serde_json::to_vec_pretty(value).expect("Failed to serialize response");
+        body.push(b'\n');
+        Response { status, content_type: "application/json", body }
+    }
+
+    /// 返回 `{"error": message}`
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+
+    /// 没有响应体
+    pub fn empty(status: u16) -> Self {
+        Response { status, content_type: "application/json", body: Vec::new() }
+    }
+}
+
+/// 读取连接上的一个请求，连接关闭或请求无法解析时返回错误响应
+pub async fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buffer.len() > MAX_BODY_SIZE {
+            return Err(Response::error(431, "Request headers too large"));
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Err(Response::error(400, "Incomplete request")),
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(Response::error(400, "Malformed request line"));
+    };
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>())
+        .transpose()
+        .map_err(|_| Response::error(400, "Invalid Content-Length"))?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        return Err(Response::error(413, "Request body too large"));
+    }
+
+    let mut body = buffer.split_off(header_end);
+    while body.len() < content_length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Err(Response::error(400, "Incomplete request body")),
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+    body.truncate(content_length);
+
+    // 借助 URL 解析拆分路径和查询参数
+    let url = Url::parse(&format!("http://localhost{}", target)).map_err(|_| Response::error(400, "Malformed request target"))?;
+    let path = url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).map(percent_decode).collect())
+        .unwrap_or_default();
+    let query = url.query_pairs().into_owned().collect();
+    Ok(Request { method: method.to_string(), path, query, body })
+}
+
+/// 写出响应并关闭连接
+pub async fn write_response(stream: &mut TcpStream, response: Response) {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    if stream.write_all(head.as_bytes()).await.is_ok() {
+        let _ = stream.write_all(&response.body).await;
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+// 解码路径段中的 %XX，无效的编码原样保留
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,6 @@
+pub mod api;  // 导入 api.rs 文件
+pub mod http;  // 导入 http.rs 文件
+pub mod supervisor;  // 导入 supervisor.rs 文件
+
+pub use self::api::serve;
+pub use self::supervisor::Supervisor;
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use chrono::Utc;
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use crate::store::{TaskRef, TaskStore, DEFAULT_STORE_PATH};
+use crate::worker::{RunControl, RunTrigger, TaskManager};
+use crate::worker::run_lock::RunLock;
+// 日志宏
+use log::{info, warn, error};
+
+/// 检查任务计划的间隔
+pub const SCHEDULE_POLL: Duration = Duration::from_secs(30);
+
+// 服务进程中运行的一个任务
+struct RunHandle {
+    name: String,
+    control: Arc<RunControl>,
+    thread: JoinHandle<()>,
+}
+
+/// 在同一进程中并发运行多个任务，每个任务在单独的线程中运行，共用一个任务存储
+pub struct Supervisor {
+    store: Arc<TaskStore>,
+    runs: Mutex<HashMap<u32, RunHandle>>,
+    // 服务模式下不绘制进度条
+    progress: Arc<MultiProgress>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor {
+            store: Arc::new(TaskStore::open(DEFAULT_STORE_PATH).expect("Failed to open task store")),
+            runs: Mutex::new(HashMap::new()),
+            progress: Arc::new(MultiProgress::with_draw_target(ProgressDrawTarget::hidden())),
+        }
+    }
+
+    /// 使用共享任务存储的任务管理器
+    pub fn task_manager(&self) -> TaskManager {
+        TaskManager::with_store(Arc::clone(&self.store))
+    }
+
+    /// 在新线程中运行已加载的任务，任务已在本进程或其他进程中运行时返回错误
+    pub fn start(&self, mut task_manager: TaskManager) -> Result<(), String> {
+        let task = task_manager.task_info().clone();
+        let mut runs = self.runs.lock().unwrap();
+        runs.retain(|_, run| !run.thread.is_finished());
+        if runs.contains_key(&task.id) {
+            return Err(format!("Task {} ({}) is already running", task.id, task.name));
+        }
+        if let Some(pid) = RunLock::holder(&format!("tasks/{}", task.name)) {
+            return Err(format!("Task {} ({}) is running in process {}", task.id, task.name, pid));
+        }
+
+        let control = task_manager.run_control();
+        let progress = Arc::clone(&self.progress);
+        let thread = thread::Builder::new()
+            .name(format!("task-{}", task.id))
+            .spawn(move || {
+                if let Err(e) = task_manager.run(&progress, None) {
+                    error!("{}", e);
+                }
+            })
+            .map_err(|e| format!("Failed to start task {}: {}", task.id, e))?;
+        info!("Started task {} ({})", task.id, task.name);
+        runs.insert(task.id, RunHandle { name: task.name, control, thread });
+        Ok(())
+    }
+
+    /// 任务是否正在本进程中运行
+    pub fn is_running(&self, task_id: u32) -> bool {
+        self.runs.lock().unwrap().get(&task_id).is_some_and(|run| !run.thread.is_finished())
+    }
+
+    /// 停止本进程中运行的任务，进行中的请求完成后保存状态；任务没有在本进程中运行时返回 false
+    pub fn stop(&self, task_id: u32) -> bool {
+        let runs = self.runs.lock().unwrap();
+        let Some(run) = runs.get(&task_id).filter(|run| !run.thread.is_finished()) else {
+            return false;
+        };
+        info!("Stopping task {} ({})", task_id, run.name);
+        run.control.stop();
+        true
+    }
+
+    /// 启动计划时间已到的任务：与 restart 相同，清空进度后从种子 URL 重新运行；正在运行的任务等它结束后再启动
+    pub fn start_due(&self) {
+        let scheduled = match self.store.scheduled_tasks() {
+            Ok(scheduled) => scheduled,
+            Err(e) => {
+                error!("Failed to load scheduled tasks: {}", e);
+                return;
+            }
+        };
+        let now = Utc::now();
+        for (task, next_run) in scheduled {
+            if next_run > now || self.is_running(task.id) || RunLock::holder(&format!("tasks/{}", task.name)).is_some() {
+                continue;
+            }
+            let mut task_manager = self.task_manager();
+            if !task_manager.reset_task(&TaskRef::Id(task.id)) {
+                continue;
+            }
+            task_manager.set_trigger(RunTrigger::Schedule);
+            info!("Task {} ({}) is due since {}", task.id, task.name, next_run.to_rfc3339());
+            if let Err(e) = self.start(task_manager) {
+                warn!("Failed to start scheduled run: {}", e);
+            }
+        }
+    }
+
+    /// 停止所有任务并等待它们保存状态后退出
+    pub fn shutdown(&self) {
+        let runs: Vec<_> = self.runs.lock().unwrap().drain().collect();
+        for (_, run) in &runs {
+            run.control.stop();
+        }
+        for (task_id, run) in runs {
+            info!("Waiting for task {} ({}) to stop...", task_id, run.name);
+            if run.thread.join().is_err() {
+                error!("Task {} ({}) panicked", task_id, run.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Serialize, Deserialize};
+use crate::worker::TaskInfo;
+use crate::worker::control::PAUSE_FILE;
+use crate::worker::run_lock::LOCK_FILE;
+use super::checkpoint::CHECKPOINT_FILE;
+use super::report::PageCounts;
+use super::{validate_task_name, TaskStore};
+
+/// 归档格式版本，导入时不兼容的版本会被拒绝
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DATABASE_FILE: &str = "task.db";
+const FILES_DIR: &str = "files";
+// 任务目录中不需要归档的运行时文件
+const EXCLUDED_FILES: [&str; 3] = [CHECKPOINT_FILE, LOCK_FILE, PAUSE_FILE];
+
+/// 归档清单，描述归档中的任务和文件
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub archived_at: String,
+    pub task: TaskInfo,
+    pub pages: PageCounts,
+    /// 任务目录中被归档的文件，相对于任务目录
+    pub files: Vec<String>,
+}
+
+/// 把任务的数据库记录和任务目录打包成 tar.gz，归档内容为 `manifest.json`、`task.db` 和 `files/`
+pub fn create(store: &TaskStore, task_id: u32, task_dir: &Path, output: &Path) -> Result<Manifest, Box<dyn Error>> {
+    let report = store.report(task_id)?.ok_or("task not found")?;
+    let files = if task_dir.is_dir() { list_files(task_dir, task_dir)? } else { Vec::new() };
+    let manifest = Manifest {
+        version: ARCHIVE_VERSION,
+        archived_at: Utc::now().to_rfc3339(),
+        task: report.task,
+        pages: report.pages,
+        files: files.iter().map(|file| file.to_string_lossy().into_owned()).collect(),
+    };
+
+    let database = std::env::temp_dir().join(format!("spider-archive-{}-{}.db", task_id, std::process::id()));
+    let _ = fs::remove_file(&database);
+    store.export_task(task_id, &database.to_string_lossy())?;
+
+    // 先写入临时文件，完成后再重命名，避免留下不完整的归档
+    let mut tmp_name = output.file_name().ok_or("invalid output path")?.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_output = output.with_file_name(tmp_name);
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(&tmp_output)?, Compression::default()));
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        builder.append_data(&mut header, MANIFEST_FILE, manifest_json.as_slice())?;
+        builder.append_path_with_name(&database, DATABASE_FILE)?;
+        for file in &files {
+            builder.append_path_with_name(task_dir.join(file), Path::new(FILES_DIR).join(file))?;
+        }
+        builder.into_inner()?.finish()?.sync_all()?;
+        fs::rename(&tmp_output, output)?;
+        Ok(())
+    })();
+    let _ = fs::remove_file(&database);
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_output);
+    }
+    result.map(|_| manifest)
+}
+
+/// 解包后的归档，保存在临时目录中，释放时删除
+pub struct Unpacked {
+    pub manifest: Manifest,
+    dir: PathBuf,
+}
+
+impl Unpacked {
+    /// 读取归档并解包到 `staging_dir`，清单缺失或版本不兼容时返回错误
+    pub fn open(archive: &Path, staging_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let _ = fs::remove_dir_all(staging_dir);
+        fs::create_dir_all(staging_dir)?;
+        match unpack(archive, staging_dir) {
+            Ok(manifest) => Ok(Unpacked { manifest, dir: staging_dir.to_path_buf() }),
+            Err(e) => {
+                let _ = fs::remove_dir_all(staging_dir);
+                Err(e)
+            }
+        }
+    }
+
+    /// 把任务记录导入任务存储，并把归档中的任务目录移动到 `task_dir`；任务名无效时不做任何改动
+    pub fn restore(&self, store: &TaskStore, name: &str, task_dir: &Path) -> Result<TaskInfo, Box<dyn Error>> {
+        validate_task_name(name)?;
+        let task_info = store.import_task(&self.dir.join(DATABASE_FILE).to_string_lossy(), name)?;
+        let files = self.dir.join(FILES_DIR);
+        if files.is_dir() {
+            if task_dir.exists() {
+                fs::remove_dir_all(task_dir)?;
+            }
+            fs::rename(&files, task_dir)?;
+        } else {
+            fs::create_dir_all(task_dir)?;
+        }
+        Ok(task_info)
+    }
+}
+
+impl Drop for Unpacked {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn unpack(archive: &Path, staging_dir: &Path) -> Result<Manifest, Box<dyn Error>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    for entry in archive.entries()? {
+        // unpack_in 会拒绝包含 `..` 的路径，防止写到解包目录之外
+        entry?.unpack_in(staging_dir)?;
+    }
+
+    let manifest: Manifest = serde_json::from_slice(&fs::read(staging_dir.join(MANIFEST_FILE)).map_err(|_| "archive has no manifest")?)?;
+    if manifest.version != ARCHIVE_VERSION {
+        return Err(format!("unsupported archive version {}", manifest.version).into());
+    }
+    if !staging_dir.join(DATABASE_FILE).is_file() {
+        return Err("archive has no task database".into());
+    }
+    Ok(manifest)
+}
+
+// 递归列出目录下需要归档的文件，返回相对 root 的路径
+fn list_files(root: &Path, dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(list_files(root, &path)?);
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if EXCLUDED_FILES.contains(&name.as_ref()) || name.ends_with(".tmp") {
+            continue;
+        }
+        files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{CreateOutcome, TaskRef, TaskStore};
+    use crate::utils::TaskConfig;
+    use crate::worker::TaskInfo;
+    use super::*;
+
+    #[test]
+    fn restores_only_under_a_valid_name() {
+        let tmp = std::env::temp_dir().join(format!("spider-archive-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let task_dir = tmp.join("docs");
+        fs::create_dir_all(&task_dir).unwrap();
+        fs::write(task_dir.join("index.md"), "# Docs").unwrap();
+
+        let source = TaskStore::open(":memory:").unwrap();
+        let CreateOutcome::Created { id, .. } = source.create_task(&TaskInfo::new("docs".to_string(), 1), &TaskConfig::default(), &[], false).unwrap() else {
+            panic!("task name taken");
+        };
+        let archive = tmp.join("docs.tar.gz");
+        let manifest = create(&source, id, &task_dir, &archive).unwrap();
+        assert_eq!(manifest.files, vec!["index.md"]);
+
+        let target = TaskStore::open(":memory:").unwrap();
+        let unpacked = Unpacked::open(&archive, &tmp.join("staging")).unwrap();
+        assert_eq!(unpacked.manifest.task.name, "docs");
+        for name in ["", "..", "../escaped", ".hidden", "a/b"] {
+            assert!(unpacked.restore(&target, name, &tmp.join("restored")).is_err(), "{:?} should be rejected", name);
+        }
+        assert!(target.list_tasks().unwrap().is_empty());
+        assert!(!tmp.join("restored").exists());
+
+        let restored = unpacked.restore(&target, "copy", &tmp.join("copy")).unwrap();
+        assert_eq!(restored.name, "copy");
+        assert_eq!(target.resolve(&TaskRef::Name("copy".to_string())).unwrap(), Some(restored.id));
+        assert_eq!(fs::read_to_string(tmp.join("copy").join("index.md")).unwrap(), "# Docs");
+
+        drop(unpacked);
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::utils::{atomic_write, TaskConfig};
+use crate::worker::TaskInfo;
+use super::TaskRef;
+
+/// 检查点文件名，保存在任务目录中
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// 检查点配置，对应任务配置文件中的 `[checkpoint]` 段，两个条件满足其一即写入检查点
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointConfig {
+    /// 每抓取多少个页面写一次检查点，0 表示不按页数触发
+    #[serde(default = "default_every_pages")]
+    pub every_pages: u32,
+    /// 每隔多少秒写一次检查点，0 表示不按时间触发
+    #[serde(default = "default_every_secs")]
+    pub every_secs: u64,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        CheckpointConfig { every_pages: default_every_pages(), every_secs: default_every_secs() }
+    }
+}
+
+fn default_every_pages() -> u32 {
+    100
+}
+
+fn default_every_secs() -> u64 {
+    30
+}
+
+/// 抓取状态的快照：任务信息、配置、种子、待抓取队列和已访问集合，不包含抓取结果
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub task_info: TaskInfo,
+    pub config: TaskConfig,
+    pub seeds: Vec<String>,
+    pub frontier: Vec<(String, u32)>,
+    pub visited: Vec<String>,
+    pub saved_at: String,
+}
+
+impl Checkpoint {
+    /// 写入任务目录，先写临时文件再重命名，中途崩溃不会破坏上一个检查点
+    pub fn save(&self, task_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(task_dir)?;
+        atomic_write(&Path::new(task_dir).join(CHECKPOINT_FILE), &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// 读取任务目录中的检查点，文件不存在或无法解析时返回 None
+    pub fn load(task_dir: &Path) -> Option<Self> {
+        let content = fs::read(task_dir.join(CHECKPOINT_FILE)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    /// 在所有任务目录中查找指定任务的检查点
+    pub fn find(tasks_root: &str, task: &TaskRef) -> Option<Self> {
+        let dirs: Vec<PathBuf> = fs::read_dir(tasks_root).ok()?.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+        dirs.iter()
+            .filter(|dir| dir.is_dir())
+            .filter_map(|dir| Self::load(dir))
+            .find(|checkpoint| task.matches(&checkpoint.task_info))
+    }
+}
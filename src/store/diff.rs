@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use similar::TextDiff;
+
+/// `spider diff` 输出的两次运行之间的页面变化
+#[derive(Serialize)]
+pub struct RunDiff {
+    pub task_id: u32,
+    pub from: DiffRun,
+    pub to: DiffRun,
+    /// 只在较新的运行中抓取到的页面
+    pub added: Vec<String>,
+    /// 只在较早的运行中抓取到的页面
+    pub removed: Vec<String>,
+    pub modified: Vec<PageChange>,
+    pub unchanged: u32,
+}
+
+/// 参与比较的一次运行
+#[derive(Serialize)]
+pub struct DiffRun {
+    pub id: u32,
+    pub started_at: String,
+}
+
+/// 一个内容有变化的页面
+#[derive(Serialize)]
+pub struct PageChange {
+    pub url: String,
+    /// 正文的统一格式差异，页面没有可读正文时为空
+    pub diff: String,
+}
+
+impl RunDiff {
+    pub fn print(&self) {
+        println!("Comparing run {} ({}) with run {} ({}) of task {}", self.from.id, self.from.started_at, self.to.id, self.to.started_at, self.task_id);
+        println!("New: {}, Removed: {}, Modified: {}, Unchanged: {}", self.added.len(), self.removed.len(), self.modified.len(), self.unchanged);
+
+        if !self.added.is_empty() {
+            println!("\nNew pages:");
+            for url in &self.added {
+                println!("  + {}", url);
+            }
+        }
+        if !self.removed.is_empty() {
+            println!("\nRemoved pages:");
+            for url in &self.removed {
+                println!("  - {}", url);
+            }
+        }
+        if !self.modified.is_empty() {
+            println!("\nModified pages:");
+            for change in &self.modified {
+                println!("\n  ~ {}", change.url);
+                if change.diff.is_empty() {
+                    println!("    (content changed, no readable text)");
+                } else {
+                    print!("{}", change.diff);
+                }
+            }
+        }
+    }
+}
+
+// 有页面版本记录的运行（ID 和开始时间），按运行顺序排列
+pub(super) fn version_runs(conn: &Connection, task_id: u32) -> rusqlite::Result<Vec<(u32, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, started_at FROM runs WHERE task_id = ?1
+         AND EXISTS (SELECT 1 FROM page_versions WHERE task_id = ?1 AND run_id = runs.id) ORDER BY id",
+    )?;
+    let runs = stmt.query_map(params![task_id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+    runs
+}
+
+// 比较两次运行的页面版本：正文变化的页面为修改；没有可读正文的页面比较内容摘要
+pub(super) fn build(conn: &Connection, task_id: u32, from: (u32, String), to: (u32, String)) -> rusqlite::Result<RunDiff> {
+    let old = load_versions(conn, task_id, from.0)?;
+    let new = load_versions(conn, task_id, to.0)?;
+
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    let mut unchanged = 0;
+    for (url, (new_hash, new_text)) in &new {
+        let Some((old_hash, old_text)) = old.get(url) else {
+            added.push(url.clone());
+            continue;
+        };
+        if old_text != new_text {
+            let diff = TextDiff::from_lines(&format!("{}\n", old_text), &format!("{}\n", new_text))
+                .unified_diff()
+                .header(&format!("run {}", from.0), &format!("run {}", to.0))
+                .to_string();
+            modified.push(PageChange { url: url.clone(), diff });
+        } else if old_text.is_empty() && old_hash != new_hash {
+            modified.push(PageChange { url: url.clone(), diff: String::new() });
+        } else {
+            unchanged += 1;
+        }
+    }
+    let mut removed: Vec<String> = old.into_keys().filter(|url| !new.contains_key(url)).collect();
+    added.sort();
+    removed.sort();
+    modified.sort_by(|a, b| a.url.cmp(&b.url));
+
+    Ok(RunDiff {
+        task_id,
+        from: DiffRun { id: from.0, started_at: from.1 },
+        to: DiffRun { id: to.0, started_at: to.1 },
+        added,
+        removed,
+        modified,
+        unchanged,
+    })
+}
+
+// 一次运行中各 URL 的内容摘要和正文
+fn load_versions(conn: &Connection, task_id: u32, run_id: u32) -> rusqlite::Result<HashMap<String, (String, String)>> {
+    let mut stmt = conn.prepare("SELECT url, content_hash, text FROM page_versions WHERE task_id = ?1 AND run_id = ?2")?;
+    let versions = stmt.query_map(params![task_id, run_id], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?.collect();
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{CrawlStats, CreateOutcome, FetchRecord, TaskStore};
+    use crate::utils::TaskConfig;
+    use crate::worker::TaskInfo;
+
+    fn fetch(store: &TaskStore, task_id: u32, url: &str, hash: &str, text: &str) {
+        let record = FetchRecord {
+            url: url.to_string(),
+            final_url: url.to_string(),
+            depth: 1,
+            status: 200,
+            content_type: "text/html".to_string(),
+            content_length: text.len(),
+            title: None,
+            elapsed_ms: 0,
+            content_hash: hash.to_string(),
+            text: text.to_string(),
+            links: Vec::new(),
+        };
+        store.record_fetch(task_id, &record, &[]).unwrap();
+    }
+
+    #[test]
+    fn compares_pages_of_two_runs() {
+        let store = TaskStore::open(":memory:").unwrap();
+        let CreateOutcome::Created { id, .. } = store.create_task(&TaskInfo::new("docs".to_string(), 1), &TaskConfig::default(), &[], false).unwrap() else {
+            panic!("task name taken");
+        };
+        let first = store.start_run(id, "manual").unwrap();
+        fetch(&store, id, "http://a.com/removed", "h1", "gone");
+        fetch(&store, id, "http://a.com/edited", "h2", "line one\nline two");
+        fetch(&store, id, "http://a.com/same", "h3", "same");
+        fetch(&store, id, "http://a.com/image", "h4", "");
+        store.finish_run(first, "Finished", &CrawlStats::default()).unwrap();
+        let second = store.start_run(id, "manual").unwrap();
+        fetch(&store, id, "http://a.com/edited", "h5", "line one\nline 2");
+        fetch(&store, id, "http://a.com/same", "h6", "same");
+        fetch(&store, id, "http://a.com/image", "h7", "");
+        fetch(&store, id, "http://a.com/added", "h8", "new");
+
+        let runs = store.version_runs(id).unwrap();
+        assert_eq!(runs.iter().map(|(run, _)| *run).collect::<Vec<_>>(), vec![first, second]);
+        let diff = store.diff_runs(id, runs[0].clone(), runs[1].clone()).unwrap();
+        assert_eq!(diff.added, vec!["http://a.com/added"]);
+        assert_eq!(diff.removed, vec!["http://a.com/removed"]);
+        // 正文不变的页面即使摘要不同也不算修改，没有正文的页面比较摘要
+        assert_eq!(diff.unchanged, 1);
+        let modified: Vec<&str> = diff.modified.iter().map(|change| change.url.as_str()).collect();
+        assert_eq!(modified, vec!["http://a.com/edited", "http://a.com/image"]);
+        assert!(diff.modified[0].diff.contains("-line two\n+line 2\n"));
+        assert!(diff.modified[1].diff.is_empty());
+    }
+}
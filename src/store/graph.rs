@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use prettytable::{row, Table};
+use rusqlite::{params, Connection};
+
+// PageRank 的阻尼系数
+const DAMPING: f64 = 0.85;
+// PageRank 最多迭代的次数，两次迭代之间的变化小于 EPSILON 时提前结束
+const MAX_ITERATIONS: usize = 100;
+const EPSILON: f64 = 1e-9;
+
+/// `spider export-graph` 的输出格式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GraphFormat {
+    /// edges.csv 边列表和 nodes.csv 页面列表
+    Csv,
+    GraphMl,
+    Dot,
+}
+
+/// 任务的有向链接图，边中的页面为 `nodes` 的下标
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// 链接图中的一个页面，包括只被链接而没有抓取的页面
+pub struct GraphNode {
+    pub url: String,
+    /// 抓取时的状态码，没有抓取的页面为 None
+    pub status: Option<u16>,
+    pub in_degree: u32,
+    pub out_degree: u32,
+    pub pagerank: f64,
+}
+
+/// 从一个页面指向另一个页面的链接
+pub struct GraphEdge {
+    pub source: usize,
+    pub target: usize,
+    pub anchor: String,
+    pub nofollow: bool,
+    /// 来源页面的抓取深度
+    pub depth: u32,
+}
+
+impl LinkGraph {
+    /// 按格式把链接图写入 `dir`，返回写出的文件
+    pub fn export(&self, name: &str, dir: &Path, format: GraphFormat) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        match format {
+            GraphFormat::Csv => {
+                let (edges, nodes) = (dir.join("edges.csv"), dir.join("nodes.csv"));
+                self.write_edges_csv(&mut BufWriter::new(File::create(&edges)?))?;
+                self.write_nodes_csv(&mut BufWriter::new(File::create(&nodes)?))?;
+                Ok(vec![edges, nodes])
+            }
+            GraphFormat::GraphMl => {
+                let path = dir.join("graph.graphml");
+                self.write_graphml(name, &mut BufWriter::new(File::create(&path)?))?;
+                Ok(vec![path])
+            }
+            GraphFormat::Dot => {
+                let path = dir.join("graph.dot");
+                self.write_dot(name, &mut BufWriter::new(File::create(&path)?))?;
+                Ok(vec![path])
+            }
+        }
+    }
+
+    fn write_edges_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "source,target,anchor,nofollow,depth")?;
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&self.nodes[edge.source].url),
+                csv_field(&self.nodes[edge.target].url),
+                csv_field(&edge.anchor),
+                edge.nofollow,
+                edge.depth
+            )?;
+        }
+        out.flush()
+    }
+
+    fn write_nodes_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "url,status,in_degree,out_degree,pagerank")?;
+        for node in &self.nodes {
+            let status = node.status.map(|status| status.to_string()).unwrap_or_default();
+            writeln!(out, "{},{},{},{},{:.8}", csv_field(&node.url), status, node.in_degree, node.out_degree, node.pagerank)?;
+        }
+        out.flush()
+    }
+
+    fn write_graphml(&self, name: &str, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(out, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        for (id, target, kind) in [
+            ("url", "node", "string"),
+            ("status", "node", "int"),
+            ("in_degree", "node", "int"),
+            ("out_degree", "node", "int"),
+            ("pagerank", "node", "double"),
+            ("anchor", "edge", "string"),
+            ("nofollow", "edge", "boolean"),
+            ("depth", "edge", "int"),
+        ] {
+            writeln!(out, "  <key id=\"{0}\" for=\"{1}\" attr.name=\"{0}\" attr.type=\"{2}\"/>", id, target, kind)?;
+        }
+        writeln!(out, "  <graph id=\"{}\" edgedefault=\"directed\">", xml_escape(name))?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            write!(out, "    <node id=\"n{}\"><data key=\"url\">{}</data>", index, xml_escape(&node.url))?;
+            if let Some(status) = node.status {
+                write!(out, "<data key=\"status\">{}</data>", status)?;
+            }
+            writeln!(
+                out,
+                "<data key=\"in_degree\">{}</data><data key=\"out_degree\">{}</data><data key=\"pagerank\">{:.8}</data></node>",
+                node.in_degree, node.out_degree, node.pagerank
+            )?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "    <edge source=\"n{}\" target=\"n{}\"><data key=\"anchor\">{}</data><data key=\"nofollow\">{}</data><data key=\"depth\">{}</data></edge>",
+                edge.source, edge.target, xml_escape(&edge.anchor), edge.nofollow, edge.depth
+            )?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")?;
+        out.flush()
+    }
+
+    fn write_dot(&self, name: &str, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "digraph \"{}\" {{", dot_escape(name))?;
+        for node in &self.nodes {
+            writeln!(
+                out,
+                "  \"{}\" [in_degree={}, out_degree={}, pagerank={:.8}{}];",
+                dot_escape(&node.url), node.in_degree, node.out_degree, node.pagerank,
+                if node.status.is_none() { ", style=dashed" } else { "" }
+            )?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "  \"{}\" -> \"{}\" [label=\"{}\", depth={}{}];",
+                dot_escape(&self.nodes[edge.source].url),
+                dot_escape(&self.nodes[edge.target].url),
+                dot_escape(&edge.anchor),
+                edge.depth,
+                if edge.nofollow { ", style=dashed" } else { "" }
+            )?;
+        }
+        writeln!(out, "}}")?;
+        out.flush()
+    }
+
+    /// 打印 PageRank 最高的 `top` 个页面
+    pub fn print_top(&self, top: usize) {
+        let mut ranked: Vec<&GraphNode> = self.nodes.iter().collect();
+        ranked.sort_by(|a, b| b.pagerank.total_cmp(&a.pagerank).then_with(|| a.url.cmp(&b.url)));
+        let mut table = Table::new();
+        table.add_row(row!["Rank", "Page", "PageRank", "In-Degree", "Out-Degree"]);
+        for (index, node) in ranked.iter().take(top).enumerate() {
+            table.add_row(row![index + 1, node.url, format!("{:.6}", node.pagerank), node.in_degree, node.out_degree]);
+        }
+        table.printstd();
+    }
+}
+
+// 从任务存储中读取链接图并计算入度、出度和 PageRank
+pub(super) fn load(conn: &Connection, task_id: u32) -> rusqlite::Result<LinkGraph> {
+    let mut nodes = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut node_index = |nodes: &mut Vec<GraphNode>, url: String| {
+        *index.entry(url.clone()).or_insert_with(|| {
+            nodes.push(GraphNode { url, status: None, in_degree: 0, out_degree: 0, pagerank: 0.0 });
+            nodes.len() - 1
+        })
+    };
+
+    let mut stmt = conn.prepare("SELECT url, status FROM fetch_results WHERE task_id = ?1 ORDER BY rowid")?;
+    let mut rows = stmt.query(params![task_id])?;
+    while let Some(row) = rows.next()? {
+        let node = node_index(&mut nodes, row.get(0)?);
+        nodes[node].status = Some(row.get(1)?);
+    }
+
+    let mut edges = Vec::new();
+    let mut stmt = conn.prepare("SELECT source, target, anchor, nofollow, depth FROM links WHERE task_id = ?1 ORDER BY rowid")?;
+    let mut rows = stmt.query(params![task_id])?;
+    while let Some(row) = rows.next()? {
+        let source = node_index(&mut nodes, row.get(0)?);
+        let target = node_index(&mut nodes, row.get(1)?);
+        nodes[source].out_degree += 1;
+        nodes[target].in_degree += 1;
+        edges.push(GraphEdge { source, target, anchor: row.get(2)?, nofollow: row.get(3)?, depth: row.get(4)? });
+    }
+
+    let ranks = pagerank(nodes.len(), &edges);
+    for (node, rank) in nodes.iter_mut().zip(ranks) {
+        node.pagerank = rank;
+    }
+    Ok(LinkGraph { nodes, edges })
+}
+
+// 幂迭代计算 PageRank，nofollow 链接不传递权重；没有出链的页面把权重平均分给所有页面
+fn pagerank(count: usize, edges: &[GraphEdge]) -> Vec<f64> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let followed: Vec<&GraphEdge> = edges.iter().filter(|edge| !edge.nofollow && edge.source != edge.target).collect();
+    let mut out_links = vec![0u32; count];
+    for edge in &followed {
+        out_links[edge.source] += 1;
+    }
+
+    let n = count as f64;
+    let mut ranks = vec![1.0 / n; count];
+    for _ in 0..MAX_ITERATIONS {
+        let dangling: f64 = ranks.iter().zip(&out_links).filter(|(_, links)| **links == 0).map(|(rank, _)| rank).sum();
+        let mut next = vec![(1.0 - DAMPING) / n + DAMPING * dangling / n; count];
+        for edge in &followed {
+            next[edge.target] += DAMPING * ranks[edge.source] / out_links[edge.source] as f64;
+        }
+        let delta: f64 = next.iter().zip(&ranks).map(|(a, b)| (a - b).abs()).sum();
+        ranks = next;
+        if delta < EPSILON {
+            break;
+        }
+    }
+    ranks
+}
+
+// 包含逗号、引号或换行的 CSV 字段需要加引号，字段中的引号写两次
+pub(super) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,14 @@
+pub mod archive;
+pub mod checkpoint;
+pub mod diff;
+pub mod graph;
+pub mod report;
+pub mod stats;
+pub mod task_store;
+
+pub use self::checkpoint::{Checkpoint, CheckpointConfig};
+pub use self::diff::RunDiff;
+pub use self::graph::{GraphFormat, LinkGraph};
+pub use self::report::TaskReport;
+pub use self::stats::CrawlStats;
+pub use self::task_store::{validate_task_name, CreateOutcome, FetchRecord, RunRecord, TaskRef, TaskStore, DEFAULT_STORE_PATH};
@@ -0,0 +1,468 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use prettytable::{row, Table};
+use reqwest::Url;
+use rusqlite::{params, Connection};
+use serde::{Serialize, Deserialize};
+use chrono::Local;
+use crate::utils::format::format_bytes;
+use crate::utils::TaskConfig;
+use crate::worker::TaskInfo;
+use super::graph::csv_field;
+use super::task_store::{load_runs, next_run};
+use super::RunRecord;
+
+/// `spider show` 输出的任务报告
+#[derive(Serialize)]
+pub struct TaskReport {
+    pub task: TaskInfo,
+    pub seeds: Vec<String>,
+    /// 隐去认证信息后的任务配置
+    pub config: TaskConfig,
+    pub pages: PageCounts,
+    pub skipped_by_reason: BTreeMap<String, u32>,
+    pub status_codes: BTreeMap<u16, u32>,
+    pub depths: BTreeMap<u32, u32>,
+    pub bytes_downloaded: u64,
+    pub avg_latency_ms: Option<f64>,
+    pub hosts: Vec<HostStats>,
+    pub top_errors: Vec<ErrorCount>,
+    /// 按任务计划的下一次运行时间（本地时间）
+    pub next_run: Option<String>,
+    /// 最近的运行记录，最新的在前
+    pub runs: Vec<RunRecord>,
+    /// 近似重复的页面，按重复页面数从多到少
+    pub duplicates: Vec<DuplicateCluster>,
+    /// 死链及引用它们的页面，只有开启死链检查的任务才有
+    pub broken_links: Vec<BrokenLink>,
+    pub outputs: Outputs,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PageCounts {
+    pub fetched: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    /// 仍在待抓取队列中的 URL 数
+    pub queued: u32,
+}
+
+#[derive(Serialize, Default)]
+pub struct HostStats {
+    pub host: String,
+    pub fetched: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub bytes: u64,
+    pub avg_latency_ms: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct ErrorCount {
+    pub message: String,
+    pub count: u32,
+}
+
+/// 一组近似重复的页面
+#[derive(Serialize)]
+pub struct DuplicateCluster {
+    /// 最先抓取的页面
+    pub url: String,
+    pub duplicates: Vec<DuplicatePage>,
+}
+
+#[derive(Serialize)]
+pub struct DuplicatePage {
+    pub url: String,
+    /// 与最先抓取页面的指纹相似度
+    pub similarity: f64,
+}
+
+/// 返回错误状态码或无法访问的链接
+#[derive(Serialize)]
+pub struct BrokenLink {
+    pub url: String,
+    pub status: Option<u16>,
+    /// 请求失败的原因，例如 timeout 或 connection failed
+    pub error: Option<String>,
+    /// 是否为只检查不抓取的范围外链接
+    pub external: bool,
+    pub referrers: Vec<Referrer>,
+}
+
+/// 包含死链的页面
+#[derive(Serialize)]
+pub struct Referrer {
+    pub page: String,
+    pub anchor: String,
+}
+
+/// 任务相关文件的位置
+#[derive(Serialize, Default)]
+pub struct Outputs {
+    pub store: String,
+    pub task_dir: String,
+    pub cache_dir: Option<String>,
+    pub checkpoint: Option<String>,
+}
+
+// 报告中最多列出的错误信息条数
+const TOP_ERRORS: usize = 10;
+// 报告中最多列出的运行记录条数
+const RECENT_RUNS: usize = 10;
+// 表格中最多列出的近似重复页面组数
+const TOP_CLUSTERS: usize = 10;
+// 表格中最多列出的死链数，以及每个死链列出的引用页面数
+const TOP_BROKEN_LINKS: usize = 20;
+const TOP_REFERRERS: usize = 3;
+
+// 从任务存储中汇总报告，文件位置由调用方填写
+pub(super) fn build(conn: &Connection, task_info: TaskInfo, config: TaskConfig) -> rusqlite::Result<TaskReport> {
+    let task_id = task_info.id;
+    let seeds = query_column(conn, "SELECT url FROM seeds WHERE task_id = ?1 ORDER BY rowid", task_id)?;
+
+    let mut hosts: HashMap<String, HostStats> = HashMap::new();
+    let mut latency: HashMap<String, (u64, u32)> = HashMap::new();
+    let mut status_codes = BTreeMap::new();
+    let mut depths = BTreeMap::new();
+    let mut pages = PageCounts::default();
+    let mut bytes_downloaded = 0;
+    let mut total_latency = (0, 0);
+
+    let mut stmt = conn.prepare("SELECT url, status, depth, content_length, elapsed_ms FROM fetch_results WHERE task_id = ?1")?;
+    let mut rows = stmt.query(params![task_id])?;
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(0)?;
+        let content_length = row.get::<_, i64>(3)? as u64;
+        let elapsed_ms = row.get::<_, i64>(4)? as u64;
+        *status_codes.entry(row.get(1)?).or_insert(0) += 1;
+        *depths.entry(row.get(2)?).or_insert(0) += 1;
+        pages.fetched += 1;
+        bytes_downloaded += content_length;
+
+        let host = host_of(&url);
+        let stats = host_entry(&mut hosts, &host);
+        stats.fetched += 1;
+        stats.bytes += content_length;
+        // 旧版本没有记录耗时，这些记录不参与平均值
+        if elapsed_ms > 0 {
+            let entry = latency.entry(host).or_insert((0, 0));
+            *entry = (entry.0 + elapsed_ms, entry.1 + 1);
+            total_latency = (total_latency.0 + elapsed_ms, total_latency.1 + 1);
+        }
+    }
+
+    for url in query_column(conn, "SELECT url FROM errors WHERE task_id = ?1", task_id)? {
+        pages.failed += 1;
+        host_entry(&mut hosts, &host_of(&url)).failed += 1;
+    }
+
+    let mut skipped_by_reason = BTreeMap::new();
+    let mut stmt = conn.prepare("SELECT url, reason FROM skipped WHERE task_id = ?1")?;
+    let mut rows = stmt.query(params![task_id])?;
+    while let Some(row) = rows.next()? {
+        pages.skipped += 1;
+        *skipped_by_reason.entry(row.get(1)?).or_insert(0) += 1;
+        host_entry(&mut hosts, &host_of(&row.get::<_, String>(0)?)).skipped += 1;
+    }
+
+    pages.queued = conn.query_row("SELECT COUNT(*) FROM frontier WHERE task_id = ?1", params![task_id], |row| row.get(0))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT message, COUNT(*) AS count FROM errors WHERE task_id = ?1 GROUP BY message ORDER BY count DESC, message LIMIT ?2",
+    )?;
+    let top_errors = stmt
+        .query_map(params![task_id, TOP_ERRORS as i64], |row| Ok(ErrorCount { message: row.get(0)?, count: row.get(1)? }))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut hosts: Vec<HostStats> = hosts.into_values().collect();
+    for stats in &mut hosts {
+        stats.avg_latency_ms = latency.get(&stats.host).map(|(total, count)| *total as f64 / *count as f64);
+    }
+    hosts.sort_by(|a, b| b.fetched.cmp(&a.fetched).then_with(|| a.host.cmp(&b.host)));
+
+    let next_run = next_run(conn, &task_info, &config)?.map(|next| next.with_timezone(&Local).to_rfc3339());
+    let runs = load_runs(conn, task_id, Some(RECENT_RUNS))?;
+    let duplicates = duplicate_clusters(conn, task_id)?;
+    let broken_links = if config.check_links.enabled { broken_links(conn, task_id)? } else { Vec::new() };
+
+    Ok(TaskReport {
+        task: task_info,
+        seeds,
+        config: config.redacted(),
+        pages,
+        skipped_by_reason,
+        status_codes,
+        depths,
+        bytes_downloaded,
+        avg_latency_ms: (total_latency.1 > 0).then(|| total_latency.0 as f64 / total_latency.1 as f64),
+        hosts,
+        top_errors,
+        next_run,
+        runs,
+        duplicates,
+        broken_links,
+        outputs: Outputs::default(),
+    })
+}
+
+impl TaskReport {
+    /// 以表格形式打印报告
+    pub fn print_table(&self) {
+        let task = &self.task;
+        let mut overview = Table::new();
+        overview.add_row(row!["Task ID", task.id]);
+        overview.add_row(row!["Task Name", task.name]);
+        overview.add_row(row!["Status", task.status]);
+        overview.add_row(row!["Deep", task.deep]);
+        overview.add_row(row!["Created At", task.created_at.as_deref().unwrap_or("N/A")]);
+        overview.add_row(row!["Ended At", task.ended_at.as_deref().unwrap_or("N/A")]);
+        if let Some(reason) = &task.finish_reason {
+            overview.add_row(row!["Finish Reason", reason]);
+        }
+        if let Some(cron) = &self.config.schedule.cron {
+            overview.add_row(row!["Schedule", cron]);
+            overview.add_row(row!["Next Run", self.next_run.as_deref().unwrap_or("N/A")]);
+        }
+        overview.add_row(row!["Fetched", self.pages.fetched]);
+        overview.add_row(row!["Failed", self.pages.failed]);
+        overview.add_row(row!["Skipped", self.pages.skipped]);
+        overview.add_row(row!["Queued", self.pages.queued]);
+        overview.add_row(row!["Downloaded", format_bytes(self.bytes_downloaded)]);
+        overview.add_row(row!["Avg Latency", format_latency(self.avg_latency_ms)]);
+        overview.printstd();
+
+        println!("\nSeeds:");
+        for seed in &self.seeds {
+            println!("  {}", seed);
+        }
+
+        println!("\nConfig:");
+        match toml::to_string(&self.config) {
+            Ok(config) => print!("{}", config),
+            Err(_) => println!("{}", serde_json::to_string_pretty(&self.config).unwrap_or_default()),
+        }
+
+        print_counts("Status Codes", "Status", &self.status_codes);
+        print_counts("Depths", "Depth", &self.depths);
+        print_counts("Skipped", "Reason", &self.skipped_by_reason);
+
+        if !self.hosts.is_empty() {
+            println!("\nHosts:");
+            let mut table = Table::new();
+            table.add_row(row!["Host", "Fetched", "Failed", "Skipped", "Downloaded", "Avg Latency"]);
+            for host in &self.hosts {
+                table.add_row(row![
+                    host.host,
+                    host.fetched,
+                    host.failed,
+                    host.skipped,
+                    format_bytes(host.bytes),
+                    format_latency(host.avg_latency_ms)
+                ]);
+            }
+            table.printstd();
+        }
+
+        if !self.top_errors.is_empty() {
+            println!("\nTop Errors:");
+            let mut table = Table::new();
+            table.add_row(row!["Count", "Message"]);
+            for error in &self.top_errors {
+                table.add_row(row![error.count, error.message]);
+            }
+            table.printstd();
+        }
+
+        if !self.runs.is_empty() {
+            println!("\nRecent Runs:");
+            let mut table = Table::new();
+            table.add_row(row!["Run", "Trigger", "Status", "Started At", "Duration", "Fetched", "Failed", "Skipped", "Downloaded"]);
+            for run in &self.runs {
+                match &run.stats {
+                    Some(stats) => table.add_row(row![
+                        run.id,
+                        run.triggered_by,
+                        run.status,
+                        run.started_at,
+                        format!("{:.1} s", stats.duration_secs),
+                        stats.fetched,
+                        stats.failed,
+                        stats.skipped,
+                        format_bytes(stats.bytes)
+                    ]),
+                    None => table.add_row(row![run.id, run.triggered_by, run.status, run.started_at, "", "", "", "", ""]),
+                };
+            }
+            table.printstd();
+        }
+
+        print_duplicates(&self.duplicates);
+        if self.config.check_links.enabled {
+            print_broken_links(&self.broken_links);
+        }
+
+        println!("\nOutputs:");
+        let mut table = Table::new();
+        table.add_row(row!["Task Store", self.outputs.store]);
+        table.add_row(row!["Task Directory", self.outputs.task_dir]);
+        table.add_row(row!["HTTP Cache", self.outputs.cache_dir.as_deref().unwrap_or("N/A")]);
+        table.add_row(row!["Checkpoint", self.outputs.checkpoint.as_deref().unwrap_or("N/A")]);
+        table.printstd();
+    }
+}
+
+/// 打印最大的几组近似重复页面
+pub fn print_duplicates(clusters: &[DuplicateCluster]) {
+    if clusters.is_empty() {
+        return;
+    }
+    let pages: usize = clusters.iter().map(|cluster| cluster.duplicates.len()).sum();
+    println!("\nNear Duplicates: {} pages in {} groups", pages, clusters.len());
+    let mut table = Table::new();
+    table.add_row(row!["Page", "Duplicate", "Similarity"]);
+    for cluster in clusters.iter().take(TOP_CLUSTERS) {
+        for (index, duplicate) in cluster.duplicates.iter().enumerate() {
+            let page = if index == 0 { cluster.url.as_str() } else { "" };
+            table.add_row(row![page, duplicate.url, format!("{:.0}%", duplicate.similarity * 100.0)]);
+        }
+    }
+    table.printstd();
+    if clusters.len() > TOP_CLUSTERS {
+        println!("{} more groups, see `spider show --json`", clusters.len() - TOP_CLUSTERS);
+    }
+}
+
+// 按最先抓取的页面分组的近似重复页面，重复页面多的组在前
+pub(super) fn duplicate_clusters(conn: &Connection, task_id: u32) -> rusqlite::Result<Vec<DuplicateCluster>> {
+    let mut stmt = conn.prepare(
+        "SELECT duplicate_of, url, similarity FROM fingerprints WHERE task_id = ?1 AND duplicate_of IS NOT NULL
+         ORDER BY duplicate_of, similarity DESC, url",
+    )?;
+    let mut rows = stmt.query(params![task_id])?;
+    let mut clusters: Vec<DuplicateCluster> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(0)?;
+        let duplicate = DuplicatePage { url: row.get(1)?, similarity: row.get(2)? };
+        match clusters.last_mut() {
+            Some(cluster) if cluster.url == url => cluster.duplicates.push(duplicate),
+            _ => clusters.push(DuplicateCluster { url, duplicates: vec![duplicate] }),
+        }
+    }
+    clusters.sort_by(|a, b| b.duplicates.len().cmp(&a.duplicates.len()).then_with(|| a.url.cmp(&b.url)));
+    Ok(clusters)
+}
+
+/// 打印引用页面最多的几个死链
+pub fn print_broken_links(links: &[BrokenLink]) {
+    if links.is_empty() {
+        println!("\nNo broken links found");
+        return;
+    }
+    println!("\nBroken Links: {}", links.len());
+    let mut table = Table::new();
+    table.add_row(row!["Link", "Status", "Found On", "Anchor"]);
+    for link in links.iter().take(TOP_BROKEN_LINKS) {
+        let status = link.status.map(|status| status.to_string()).or_else(|| link.error.clone()).unwrap_or_default();
+        let mut referrers = link.referrers.iter().take(TOP_REFERRERS);
+        match referrers.next() {
+            Some(first) => table.add_row(row![link.url, status, first.page, first.anchor]),
+            None => table.add_row(row![link.url, status, "(seed)", ""]),
+        };
+        for referrer in referrers {
+            table.add_row(row!["", "", referrer.page, referrer.anchor]);
+        }
+        if link.referrers.len() > TOP_REFERRERS {
+            table.add_row(row!["", "", format!("{} more pages", link.referrers.len() - TOP_REFERRERS), ""]);
+        }
+    }
+    table.printstd();
+    if links.len() > TOP_BROKEN_LINKS {
+        println!("{} more broken links, see broken-links.csv in the task directory", links.len() - TOP_BROKEN_LINKS);
+    }
+}
+
+/// 把死链写成 CSV，每个引用页面一行
+pub fn write_broken_links(links: &[BrokenLink], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "url,status,error,external,page,anchor")?;
+    for link in links {
+        let status = link.status.map(|status| status.to_string()).unwrap_or_default();
+        let error = csv_field(link.error.as_deref().unwrap_or_default());
+        if link.referrers.is_empty() {
+            writeln!(out, "{},{},{},{},,", csv_field(&link.url), status, error, link.external)?;
+        }
+        for referrer in &link.referrers {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                csv_field(&link.url), status, error, link.external, csv_field(&referrer.page), csv_field(&referrer.anchor)
+            )?;
+        }
+    }
+    out.flush()
+}
+
+// 抓取时返回错误状态码或请求失败的页面，以及检查失败的范围外链接；引用页面多的在前
+pub(super) fn broken_links(conn: &Connection, task_id: u32) -> rusqlite::Result<Vec<BrokenLink>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, status, NULL, 0 FROM fetch_results WHERE task_id = ?1 AND status >= 400
+         UNION ALL SELECT url, NULL, message, 0 FROM errors WHERE task_id = ?1 AND message NOT LIKE 'Failed to parse content%'
+         UNION ALL SELECT url, status, error, 1 FROM link_checks WHERE task_id = ?1 AND (status >= 400 OR error IS NOT NULL)",
+    )?;
+    let mut rows = stmt.query(params![task_id])?;
+    let mut links: BTreeMap<String, BrokenLink> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(0)?;
+        links.entry(url.clone()).or_insert(BrokenLink {
+            url,
+            status: row.get(1)?,
+            error: row.get(2)?,
+            external: row.get(3)?,
+            referrers: Vec::new(),
+        });
+    }
+
+    let mut stmt = conn.prepare("SELECT source, anchor FROM links WHERE task_id = ?1 AND target = ?2 ORDER BY source")?;
+    for link in links.values_mut() {
+        link.referrers = stmt
+            .query_map(params![task_id, link.url], |row| Ok(Referrer { page: row.get(0)?, anchor: row.get(1)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+    }
+    let mut links: Vec<BrokenLink> = links.into_values().collect();
+    links.sort_by(|a, b| b.referrers.len().cmp(&a.referrers.len()).then_with(|| a.url.cmp(&b.url)));
+    Ok(links)
+}
+
+pub(super) fn print_counts<K: std::fmt::Display>(title: &str, header: &str, counts: &BTreeMap<K, u32>) {
+    if counts.is_empty() {
+        return;
+    }
+    println!("\n{}:", title);
+    let mut table = Table::new();
+    table.add_row(row![header, "Count"]);
+    for (key, count) in counts {
+        table.add_row(row![key, count]);
+    }
+    table.printstd();
+}
+
+pub(super) fn format_latency(latency_ms: Option<f64>) -> String {
+    latency_ms.map(|ms| format!("{:.0} ms", ms)).unwrap_or_else(|| "N/A".to_string())
+}
+
+pub(super) fn host_of(url: &str) -> String {
+    Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn host_entry<'a>(hosts: &'a mut HashMap<String, HostStats>, host: &str) -> &'a mut HostStats {
+    hosts.entry(host.to_string()).or_insert_with(|| HostStats { host: host.to_string(), ..HostStats::default() })
+}
+
+fn query_column(conn: &Connection, sql: &str, task_id: u32) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let values = stmt.query_map(params![task_id], |row| row.get(0))?.collect();
+    values
+}
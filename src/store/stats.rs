@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use chrono::Utc;
+use prettytable::{row, Table};
+use serde::{Serialize, Deserialize};
+use crate::utils::atomic_write;
+use crate::utils::format::format_bytes;
+use super::report::{format_latency, host_of, print_counts};
+use super::FetchRecord;
+
+/// 运行统计文件名，保存在任务目录中，每次运行覆盖
+pub const STATS_FILE: &str = "stats.json";
+
+/// 请求耗时分桶的上界（毫秒），百分位数按桶估算，超过最后一个上界的耗时计入溢出桶
+pub const LATENCY_BUCKETS_MS: [u64; 13] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000, 60000];
+
+/// 一次运行的抓取统计，由 worker 在每个 URL 处理完成时更新
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CrawlStats {
+    pub task_id: u32,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub duration_secs: f64,
+    pub fetched: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    /// 下载的响应体字节数
+    pub bytes: u64,
+    /// 重试的请求数（检测到登出后重新登录再请求）
+    pub retries: u32,
+    pub status_codes: BTreeMap<u16, u32>,
+    pub content_types: BTreeMap<String, u32>,
+    pub depths: BTreeMap<u32, u32>,
+    pub hosts: BTreeMap<String, HostCounts>,
+    pub skipped_by_reason: BTreeMap<String, u32>,
+    /// 抓取成功的页面的请求耗时，没有抓取到页面时为 None
+    pub latency_ms: Option<Latency>,
+    #[serde(skip)]
+    started: Option<Instant>,
+    #[serde(skip)]
+    latencies: LatencyBuckets,
+}
+
+// 抓取成功的页面的耗时分桶计数，大小固定，复制统计时开销不随页面数增长
+#[derive(Clone, Default)]
+struct LatencyBuckets {
+    // 各桶的页面数，最后一个为溢出桶
+    counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
+}
+
+impl LatencyBuckets {
+    fn record(&mut self, ms: u64) {
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|bound| ms <= *bound).unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.min = if self.count == 0 { ms } else { self.min.min(ms) };
+        self.max = self.max.max(ms);
+        self.count += 1;
+        self.sum += ms;
+    }
+
+    // 在第 p 百分位所在的桶内按线性插值估算，结果限制在最小值和最大值之间
+    fn percentile(&self, p: u64) -> u64 {
+        let rank = (self.count * p).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            if *count == 0 || seen + count < rank {
+                seen += count;
+                continue;
+            }
+            let lower = if bucket == 0 { self.min } else { LATENCY_BUCKETS_MS[bucket - 1].max(self.min) };
+            let upper = LATENCY_BUCKETS_MS.get(bucket).map_or(self.max, |bound| (*bound).min(self.max));
+            return lower + (upper.saturating_sub(lower)) * (rank - seen) / count;
+        }
+        self.max
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct HostCounts {
+    pub fetched: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub bytes: u64,
+}
+
+/// 请求耗时的分布（毫秒），百分位数为按 `LATENCY_BUCKETS_MS` 分桶的估算值
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Latency {
+    pub min: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl CrawlStats {
+    pub fn new(task_id: u32) -> Self {
+        CrawlStats {
+            task_id,
+            started_at: Utc::now().to_rfc3339(),
+            started: Some(Instant::now()),
+            ..CrawlStats::default()
+        }
+    }
+
+    /// 记录抓取成功的页面
+    pub fn record_fetch(&mut self, record: &FetchRecord) {
+        let bytes = record.content_length as u64;
+        self.fetched += 1;
+        self.bytes += bytes;
+        *self.status_codes.entry(record.status).or_insert(0) += 1;
+        let mime = record.content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+        *self.content_types.entry(if mime.is_empty() { "unknown".to_string() } else { mime }).or_insert(0) += 1;
+        *self.depths.entry(record.depth).or_insert(0) += 1;
+        let host = self.hosts.entry(host_of(&record.url)).or_default();
+        host.fetched += 1;
+        host.bytes += bytes;
+        self.latencies.record(record.elapsed_ms);
+    }
+
+    /// 记录抓取或解析失败的 URL
+    pub fn record_failure(&mut self, url: &str, depth: u32) {
+        self.failed += 1;
+        *self.depths.entry(depth).or_insert(0) += 1;
+        self.hosts.entry(host_of(url)).or_default().failed += 1;
+    }
+
+    /// 记录被跳过的 URL 及原因
+    pub fn record_skip(&mut self, url: &str, depth: u32, reason: &str) {
+        self.skipped += 1;
+        *self.depths.entry(depth).or_insert(0) += 1;
+        *self.skipped_by_reason.entry(reason.to_string()).or_insert(0) += 1;
+        self.hosts.entry(host_of(url)).or_default().skipped += 1;
+    }
+
+    /// 更新结束时间、运行时长和耗时分布
+    pub fn finish(&mut self) {
+        self.ended_at = Some(Utc::now().to_rfc3339());
+        if let Some(started) = self.started {
+            self.duration_secs = started.elapsed().as_secs_f64();
+        }
+        let latencies = &self.latencies;
+        self.latency_ms = (latencies.count > 0).then(|| Latency {
+            min: latencies.min,
+            mean: latencies.sum as f64 / latencies.count as f64,
+            p50: latencies.percentile(50),
+            p90: latencies.percentile(90),
+            p99: latencies.percentile(99),
+            max: latencies.max,
+        });
+    }
+
+    /// 耗时不超过 `LATENCY_BUCKETS_MS` 各上界的页面数（累计），以及全部页面的耗时总和（毫秒）
+    pub fn latency_histogram(&self) -> (Vec<u64>, u64) {
+        let buckets = self.latencies.counts[..LATENCY_BUCKETS_MS.len()].iter()
+            .scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            })
+            .collect();
+        (buckets, self.latencies.sum)
+    }
+
+    /// 写入任务目录，先写临时文件再重命名
+    pub fn save(&self, task_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(task_dir)?;
+        atomic_write(&Path::new(task_dir).join(STATS_FILE), &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 打印本次运行的统计表格
+    pub fn print_table(&self) {
+        let pages = self.fetched + self.failed + self.skipped;
+        let mut overview = Table::new();
+        overview.add_row(row!["Duration", format!("{:.1} s", self.duration_secs)]);
+        overview.add_row(row!["URLs", pages]);
+        overview.add_row(row!["Fetched", self.fetched]);
+        overview.add_row(row!["Failed", self.failed]);
+        overview.add_row(row!["Skipped", self.skipped]);
+        overview.add_row(row!["Retries", self.retries]);
+        overview.add_row(row!["Downloaded", format_bytes(self.bytes)]);
+        overview.add_row(row!["Throughput", format!("{:.1} URLs/s", pages as f64 / self.duration_secs.max(0.001))]);
+        if let Some(latency) = &self.latency_ms {
+            overview.add_row(row![
+                "Latency",
+                format!(
+                    "mean {} (min {} / p50 {} / p90 {} / p99 {} / max {} ms)",
+                    format_latency(Some(latency.mean)), latency.min, latency.p50, latency.p90, latency.p99, latency.max
+                )
+            ]);
+        }
+        overview.printstd();
+
+        print_counts("Status Codes", "Status", &self.status_codes);
+        print_counts("Content Types", "Content Type", &self.content_types);
+        print_counts("Depths", "Depth", &self.depths);
+        print_counts("Skipped", "Reason", &self.skipped_by_reason);
+
+        if !self.hosts.is_empty() {
+            println!("\nHosts:");
+            let mut table = Table::new();
+            table.add_row(row!["Host", "Fetched", "Failed", "Skipped", "Downloaded"]);
+            for (host, counts) in &self.hosts {
+                table.add_row(row![host, counts.fetched, counts.failed, counts.skipped, format_bytes(counts.bytes)]);
+            }
+            table.printstd();
+        }
+    }
+}
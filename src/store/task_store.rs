@@ -0,0 +1,939 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, TransactionBehavior};
+use rusqlite::types::Type;
+use crate::parser::Link;
+use crate::utils::TaskConfig;
+use crate::worker::{Duplicate, TaskInfo, TaskStatus};
+use serde::Serialize;
+use super::{diff, graph, report, Checkpoint, CrawlStats, LinkGraph, RunDiff, TaskReport};
+use super::report::{BrokenLink, DuplicateCluster};
+// 日志宏
+use log::warn;
+
+/// 默认的任务数据库位置
+pub const DEFAULT_STORE_PATH: &str = "tasks/spider.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        info TEXT NOT NULL,
+        config TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS seeds (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (task_id, url)
+    );
+    CREATE TABLE IF NOT EXISTS frontier (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        depth INTEGER NOT NULL,
+        PRIMARY KEY (task_id, url)
+    );
+    CREATE TABLE IF NOT EXISTS visited (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (task_id, url)
+    );
+    CREATE TABLE IF NOT EXISTS fetch_results (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        final_url TEXT NOT NULL,
+        depth INTEGER NOT NULL,
+        status INTEGER NOT NULL,
+        content_type TEXT NOT NULL,
+        content_length INTEGER NOT NULL,
+        title TEXT,
+        fetched_at TEXT NOT NULL,
+        elapsed_ms INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS fetch_results_task ON fetch_results (task_id);
+    CREATE TABLE IF NOT EXISTS errors (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        message TEXT NOT NULL,
+        occurred_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS errors_task ON errors (task_id);
+    CREATE TABLE IF NOT EXISTS skipped (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        occurred_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS skipped_task ON skipped (task_id);
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        task_id INTEGER NOT NULL,
+        triggered_by TEXT NOT NULL,
+        status TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        ended_at TEXT,
+        stats TEXT
+    );
+    CREATE INDEX IF NOT EXISTS runs_task ON runs (task_id);
+    CREATE TABLE IF NOT EXISTS page_versions (
+        task_id INTEGER NOT NULL,
+        run_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        text TEXT NOT NULL,
+        PRIMARY KEY (task_id, run_id, url)
+    );
+    CREATE TABLE IF NOT EXISTS fingerprints (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        simhash INTEGER NOT NULL,
+        duplicate_of TEXT,
+        similarity REAL,
+        PRIMARY KEY (task_id, url)
+    );
+    CREATE TABLE IF NOT EXISTS links (
+        task_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        target TEXT NOT NULL,
+        anchor TEXT NOT NULL,
+        nofollow INTEGER NOT NULL,
+        depth INTEGER NOT NULL,
+        PRIMARY KEY (task_id, source, target)
+    );
+    CREATE INDEX IF NOT EXISTS links_target ON links (task_id, target);
+    CREATE TABLE IF NOT EXISTS link_checks (
+        task_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        status INTEGER,
+        error TEXT,
+        checked_at TEXT NOT NULL,
+        PRIMARY KEY (task_id, url)
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+";
+
+// 按任务保存的数据表及其除 task_id 外的列，用于删除、导出和导入任务
+const TASK_TABLES: [(&str, &str); 11] = [
+    ("seeds", "url"),
+    ("frontier", "url, depth"),
+    ("visited", "url"),
+    ("fetch_results", "url, final_url, depth, status, content_type, content_length, title, fetched_at, elapsed_ms"),
+    ("errors", "url, message, occurred_at"),
+    ("skipped", "url, reason, occurred_at"),
+    ("runs", "triggered_by, status, started_at, ended_at, stats"),
+    ("page_versions", "run_id, url, content_hash, text"),
+    ("fingerprints", "url, simhash, duplicate_of, similarity"),
+    ("links", "source, target, anchor, nofollow, depth"),
+    ("link_checks", "url, status, error, checked_at"),
+];
+
+/// 检查任务名能否用作 `tasks/` 下的任务目录名：不能为空、不能包含路径分隔符、不能以 `.` 开头
+pub fn validate_task_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid task name {:?}: must not be empty, contain / or \\, or start with .", name));
+    }
+    Ok(())
+}
+
+/// 创建任务的结果
+pub enum CreateOutcome {
+    /// 新任务的 ID，以及被替换的同名任务的 ID
+    Created { id: u32, replaced: Option<u32> },
+    /// 同名任务已存在，附带其 ID
+    NameTaken(u32),
+}
+
+/// 命令行中对任务的引用，可以是 ID 或任务名
+#[derive(Clone, Debug)]
+pub enum TaskRef {
+    Id(u32),
+    Name(String),
+    /// 未指明类型时，先按 ID 查找，找不到再按任务名查找
+    IdOrName(String),
+}
+
+impl TaskRef {
+    /// 判断任务信息是否与引用匹配，不区分 ID 是否存在于任务存储中
+    pub fn matches(&self, task_info: &TaskInfo) -> bool {
+        match self {
+            TaskRef::Id(id) => task_info.id == *id,
+            TaskRef::Name(name) => task_info.name == *name,
+            TaskRef::IdOrName(value) => value.parse() == Ok(task_info.id) || task_info.name == *value,
+        }
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "{}", id),
+            TaskRef::Name(name) | TaskRef::IdOrName(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// 一次成功抓取的记录
+pub struct FetchRecord {
+    pub url: String,
+    pub final_url: String,
+    pub depth: u32,
+    pub status: u16,
+    pub content_type: String,
+    pub content_length: usize,
+    pub title: Option<String>,
+    /// 请求耗时（毫秒），包含读取响应体
+    pub elapsed_ms: u64,
+    /// 响应体的 SHA-256 摘要
+    pub content_hash: String,
+    /// 规范化后的正文，不是内容页时为空
+    pub text: String,
+    /// 页面中的链接，已转换为绝对地址
+    pub links: Vec<Link>,
+}
+
+/// 任务的一次运行记录
+#[derive(Serialize, Clone)]
+pub struct RunRecord {
+    pub id: u32,
+    /// 触发方式：manual 或 schedule
+    pub triggered_by: String,
+    /// 运行结束时的任务状态，进程意外退出没有记录结束的运行为 Interrupted
+    pub status: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    /// 本次运行的抓取统计，与 stats.json 相同
+    pub stats: Option<CrawlStats>,
+}
+
+/// 基于 SQLite 的任务存储，保存任务信息、种子、待抓取队列、已访问集合、抓取结果和错误
+pub struct TaskStore {
+    conn: Mutex<Connection>,
+}
+
+impl TaskStore {
+    /// 打开任务数据库；数据库文件损坏时将其移到一旁并新建，任务状态之后可从检查点恢复
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = match open_connection(path) {
+            Err(e) if is_corrupt(&e) => {
+                let backup = format!("{}.corrupt-{}", path, Utc::now().timestamp());
+                warn!("Task store {} is corrupt ({}), moving it to {}", path, e, backup);
+                std::fs::rename(path, &backup)?;
+                for suffix in ["-wal", "-shm"] {
+                    let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+                }
+                open_connection(path)?
+            }
+            result => result?,
+        };
+        Ok(TaskStore { conn: Mutex::new(conn) })
+    }
+
+    /// 创建任务并写入种子，种子同时作为深度为 1 的待抓取 URL；任务 ID 在同一事务中分配。
+    /// 同名任务已存在时返回 `NameTaken`，`replace` 为 true 时在同一事务中删除同名任务
+    pub fn create_task(&self, task_info: &TaskInfo, config: &TaskConfig, seeds: &[String], replace: bool) -> rusqlite::Result<CreateOutcome> {
+        let mut conn = self.conn.lock().unwrap();
+        // 立即获取写锁，其他进程不能在检查任务名和写入之间创建同名任务
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let existing: Option<u32> = tx.query_row("SELECT id FROM tasks WHERE name = ?1", params![task_info.name], |row| row.get(0)).optional()?;
+        match existing {
+            Some(existing_id) if !replace => return Ok(CreateOutcome::NameTaken(existing_id)),
+            Some(existing_id) => delete_task_rows(&tx, existing_id)?,
+            None => {}
+        }
+        let task_id = allocate_task_id(&tx)?;
+        let task_info = TaskInfo { id: task_id, ..task_info.clone() };
+        tx.execute(
+            "INSERT INTO tasks (id, name, info, config) VALUES (?1, ?2, ?3, ?4)",
+            params![task_id, task_info.name, to_json(&task_info), to_json(config)],
+        )?;
+        for url in seeds {
+            tx.execute("INSERT OR IGNORE INTO seeds (task_id, url) VALUES (?1, ?2)", params![task_id, url])?;
+        }
+        insert_seeds_into_frontier(&tx, task_id)?;
+        tx.commit()?;
+        Ok(CreateOutcome::Created { id: task_id, replaced: existing })
+    }
+
+    /// 按 ID 或任务名查找任务 ID
+    pub fn resolve(&self, task: &TaskRef) -> rusqlite::Result<Option<u32>> {
+        let conn = self.conn.lock().unwrap();
+        let by_id = |id: u32| conn.query_row("SELECT id FROM tasks WHERE id = ?1", params![id], |row| row.get(0)).optional();
+        let by_name = |name: &str| conn.query_row("SELECT id FROM tasks WHERE name = ?1", params![name], |row| row.get(0)).optional();
+        match task {
+            TaskRef::Id(id) => by_id(*id),
+            TaskRef::Name(name) => by_name(name),
+            TaskRef::IdOrName(value) => match value.parse() {
+                Ok(id) => match by_id(id)? {
+                    Some(id) => Ok(Some(id)),
+                    None => by_name(value),
+                },
+                Err(_) => by_name(value),
+            },
+        }
+    }
+
+    /// 删除任务及其所有记录
+    pub fn delete_task(&self, task_id: u32) -> rusqlite::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        delete_task_rows(&tx, task_id)?;
+        tx.commit()
+    }
+
+    pub fn save_task(&self, task_info: &TaskInfo) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE tasks SET name = ?2, info = ?3 WHERE id = ?1",
+            params![task_info.id, task_info.name, to_json(task_info)],
+        )?;
+        Ok(())
+    }
+
+    /// 更新任务配置，例如恢复任务时提高抓取预算
+    pub fn save_config(&self, task_id: u32, config: &TaskConfig) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE tasks SET config = ?2 WHERE id = ?1", params![task_id, to_json(config)])?;
+        Ok(())
+    }
+
+    pub fn load_task(&self, task_id: u32) -> rusqlite::Result<Option<(TaskInfo, TaskConfig)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT info, config FROM tasks WHERE id = ?1", params![task_id], |row| {
+            Ok((from_json(&row.get::<_, String>(0)?)?, from_json(&row.get::<_, String>(1)?)?))
+        })
+        .optional()
+    }
+
+    /// 记录一次运行开始，返回运行 ID；之前没有记录结束的运行标记为 Interrupted。
+    /// 继续未完成的抓取时，上一次运行已抓取的页面版本复制到新的运行中，使每次运行都有完整的页面快照
+    pub fn start_run(&self, task_id: u32, triggered_by: &str) -> rusqlite::Result<u32> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE runs SET status = 'Interrupted' WHERE task_id = ?1 AND ended_at IS NULL", params![task_id])?;
+        let previous: Option<u32> = tx.query_row("SELECT MAX(id) FROM runs WHERE task_id = ?1", params![task_id], |row| row.get(0))?;
+        tx.execute(
+            "INSERT INTO runs (task_id, triggered_by, status, started_at) VALUES (?1, ?2, 'Running', ?3)",
+            params![task_id, triggered_by, Utc::now().to_rfc3339()],
+        )?;
+        let run_id = tx.last_insert_rowid() as u32;
+        let resumed: bool = tx.query_row("SELECT EXISTS (SELECT 1 FROM visited WHERE task_id = ?1)", params![task_id], |row| row.get(0))?;
+        if let (Some(previous), true) = (previous, resumed) {
+            tx.execute(
+                "INSERT INTO page_versions (task_id, run_id, url, content_hash, text)
+                 SELECT task_id, ?2, url, content_hash, text FROM page_versions WHERE task_id = ?1 AND run_id = ?3",
+                params![task_id, run_id, previous],
+            )?;
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+
+    /// 记录运行结束时的任务状态和抓取统计
+    pub fn finish_run(&self, run_id: u32, status: &str, stats: &CrawlStats) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE runs SET status = ?2, ended_at = ?3, stats = ?4 WHERE id = ?1",
+            params![run_id, status, Utc::now().to_rfc3339(), to_json(stats)],
+        )?;
+        Ok(())
+    }
+
+    /// 任务的运行记录，最新的在前，`limit` 为空时返回全部
+    pub fn load_runs(&self, task_id: u32, limit: Option<usize>) -> rusqlite::Result<Vec<RunRecord>> {
+        load_runs(&self.conn.lock().unwrap(), task_id, limit)
+    }
+
+    /// 有页面版本记录的运行（ID 和开始时间），按运行顺序排列
+    pub fn version_runs(&self, task_id: u32) -> rusqlite::Result<Vec<(u32, String)>> {
+        diff::version_runs(&self.conn.lock().unwrap(), task_id)
+    }
+
+    /// 比较任务两次运行（ID 和开始时间）抓取到的页面
+    pub fn diff_runs(&self, task_id: u32, from: (u32, String), to: (u32, String)) -> rusqlite::Result<RunDiff> {
+        diff::build(&self.conn.lock().unwrap(), task_id, from, to)
+    }
+
+    /// 任务抓取时记录的链接图
+    pub fn link_graph(&self, task_id: u32) -> rusqlite::Result<LinkGraph> {
+        graph::load(&self.conn.lock().unwrap(), task_id)
+    }
+
+    /// 任务计划的下一次运行时间，任务不存在或没有计划时为 None
+    pub fn next_run(&self, task_id: u32) -> rusqlite::Result<Option<DateTime<Utc>>> {
+        let Some((task_info, config)) = self.load_task(task_id)? else {
+            return Ok(None);
+        };
+        next_run(&self.conn.lock().unwrap(), &task_info, &config)
+    }
+
+    /// 有计划的任务及其下一次运行时间
+    pub fn scheduled_tasks(&self) -> rusqlite::Result<Vec<(TaskInfo, DateTime<Utc>)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT info, config FROM tasks ORDER BY id")?;
+        let tasks: Vec<(TaskInfo, TaskConfig)> = stmt
+            .query_map([], |row| Ok((from_json(&row.get::<_, String>(0)?)?, from_json(&row.get::<_, String>(1)?)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut scheduled = Vec::new();
+        for (task_info, config) in tasks {
+            if let Some(next) = next_run(&conn, &task_info, &config)? {
+                scheduled.push((task_info, next));
+            }
+        }
+        Ok(scheduled)
+    }
+
+    pub fn list_tasks(&self) -> rusqlite::Result<Vec<TaskInfo>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT info FROM tasks ORDER BY id")?;
+        let tasks = stmt
+            .query_map([], |row| from_json(&row.get::<_, String>(0)?))?
+            .collect();
+        tasks
+    }
+
+    pub fn load_seeds(&self, task_id: u32) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT url FROM seeds WHERE task_id = ?1 ORDER BY rowid")?;
+        let seeds = stmt.query_map(params![task_id], |row| row.get(0))?.collect();
+        seeds
+    }
+
+    pub fn load_frontier(&self, task_id: u32) -> rusqlite::Result<Vec<(String, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT url, depth FROM frontier WHERE task_id = ?1 ORDER BY depth, rowid")?;
+        let frontier = stmt
+            .query_map(params![task_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        frontier
+    }
+
+    /// 待抓取队列中尚未开始抓取的 URL 按主机统计的数量，按数量从多到少排序
+    pub fn queued_hosts(&self, task_id: u32) -> rusqlite::Result<Vec<(String, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT url FROM frontier f WHERE task_id = ?1
+             AND NOT EXISTS (SELECT 1 FROM visited v WHERE v.task_id = f.task_id AND v.url = f.url)",
+        )?;
+        let mut hosts: HashMap<String, u32> = HashMap::new();
+        for url in stmt.query_map(params![task_id], |row| row.get::<_, String>(0))? {
+            let host = Url::parse(&url?).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+            *hosts.entry(host).or_insert(0) += 1;
+        }
+        let mut hosts: Vec<_> = hosts.into_iter().collect();
+        hosts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Ok(hosts)
+    }
+
+    /// 已抓取页面的 URL 和大小，用于计算已用的抓取预算
+    pub fn load_fetched_sizes(&self, task_id: u32) -> rusqlite::Result<Vec<(String, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT url, content_length FROM fetch_results WHERE task_id = ?1")?;
+        let fetched = stmt
+            .query_map(params![task_id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?
+            .collect();
+        fetched
+    }
+
+    pub fn load_visited(&self, task_id: u32) -> rusqlite::Result<HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT url FROM visited WHERE task_id = ?1")?;
+        let visited = stmt.query_map(params![task_id], |row| row.get(0))?.collect();
+        visited
+    }
+
+    /// 标记 URL 已访问；URL 在处理完成前仍保留在待抓取队列中，崩溃后可重新入队
+    pub fn mark_visited(&self, task_id: u32, url: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("INSERT OR IGNORE INTO visited (task_id, url) VALUES (?1, ?2)", params![task_id, url])?;
+        Ok(())
+    }
+
+    /// 记录按下载限制跳过的 URL 及原因，并将其移出待抓取队列
+    pub fn record_skip(&self, task_id: u32, url: &str, reason: &str) -> rusqlite::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO skipped (task_id, url, reason, occurred_at) VALUES (?1, ?2, ?3, ?4)",
+            params![task_id, url, reason, Utc::now().to_rfc3339()],
+        )?;
+        remove_from_frontier(&tx, task_id, url)?;
+        tx.commit()
+    }
+
+    /// 把已标记访问但仍在待抓取队列中的 URL（崩溃时正在抓取）重新放回队列，返回重新入队的数量
+    pub fn requeue_in_flight(&self, task_id: u32) -> rusqlite::Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM visited WHERE task_id = ?1 AND url IN (SELECT url FROM frontier WHERE task_id = ?1)",
+            params![task_id],
+        )
+    }
+
+    /// 在一个事务中写入抓取结果和新发现的待抓取 URL，返回新加入待抓取队列的 URL 数
+    pub fn record_fetch(&self, task_id: u32, record: &FetchRecord, links: &[(String, u32)]) -> rusqlite::Result<u32> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO fetch_results (task_id, url, final_url, depth, status, content_type, content_length, title, fetched_at, elapsed_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                task_id,
+                record.url,
+                record.final_url,
+                record.depth,
+                record.status,
+                record.content_type,
+                record.content_length as i64,
+                record.title,
+                Utc::now().to_rfc3339(),
+                record.elapsed_ms as i64,
+            ],
+        )?;
+        // 成功响应的页面版本记在当前运行（最近一次未结束的运行）下，用于比较两次运行之间的变化
+        if (200..300).contains(&record.status) {
+            tx.execute(
+                "INSERT OR REPLACE INTO page_versions (task_id, run_id, url, content_hash, text)
+                 SELECT ?1, id, ?2, ?3, ?4 FROM runs WHERE task_id = ?1 AND ended_at IS NULL ORDER BY id DESC LIMIT 1",
+                params![task_id, record.url, record.content_hash, record.text],
+            )?;
+        }
+        remove_from_frontier(&tx, task_id, &record.url)?;
+        {
+            // 链接图中同一对页面之间只保留第一个链接
+            let mut insert = tx.prepare(
+                "INSERT OR IGNORE INTO links (task_id, source, target, anchor, nofollow, depth) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for link in &record.links {
+                insert.execute(params![task_id, record.url, link.href, link.text, link.nofollow, record.depth])?;
+            }
+        }
+        let mut inserted = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR IGNORE INTO frontier (task_id, url, depth) SELECT ?1, ?2, ?3
+                 WHERE NOT EXISTS (SELECT 1 FROM visited WHERE task_id = ?1 AND url = ?2)",
+            )?;
+            // 已在队列中的 URL 保留较小的深度
+            let mut update = tx.prepare("UPDATE frontier SET depth = MIN(depth, ?3) WHERE task_id = ?1 AND url = ?2")?;
+            for (url, depth) in links {
+                match insert.execute(params![task_id, url, depth])? {
+                    0 => update.execute(params![task_id, url, depth])?,
+                    count => {
+                        inserted += count as u32;
+                        count
+                    }
+                };
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// 记录页面正文的指纹，近似重复的页面同时记录与哪个页面重复
+    pub fn record_fingerprint(&self, task_id: u32, url: &str, fingerprint: u64, duplicate: Option<&Duplicate>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO fingerprints (task_id, url, simhash, duplicate_of, similarity) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![task_id, url, fingerprint as i64, duplicate.map(|d| &d.of), duplicate.map(|d| d.similarity)],
+        )?;
+        Ok(())
+    }
+
+    /// 任务已抓取的非重复页面的 URL 和指纹，用于继续抓取时的近似重复检测
+    pub fn load_fingerprints(&self, task_id: u32) -> rusqlite::Result<Vec<(String, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT url, simhash FROM fingerprints WHERE task_id = ?1 AND duplicate_of IS NULL ORDER BY rowid")?;
+        let fingerprints = stmt.query_map(params![task_id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?.collect();
+        fingerprints
+    }
+
+    /// 任务中近似重复的页面，按最先抓取的页面分组
+    pub fn duplicate_clusters(&self, task_id: u32) -> rusqlite::Result<Vec<DuplicateCluster>> {
+        report::duplicate_clusters(&self.conn.lock().unwrap(), task_id)
+    }
+
+    /// 记录范围外链接的检查结果：状态码，或请求失败的原因
+    pub fn record_link_check(&self, task_id: u32, url: &str, result: &Result<u16, String>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO link_checks (task_id, url, status, error, checked_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![task_id, url, result.as_ref().ok(), result.as_ref().err(), Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// 任务已检查过的范围外链接，继续抓取时不再重复检查
+    pub fn load_checked_links(&self, task_id: u32) -> rusqlite::Result<HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT url FROM link_checks WHERE task_id = ?1")?;
+        let checked = stmt.query_map(params![task_id], |row| row.get(0))?.collect();
+        checked
+    }
+
+    /// 任务中的死链及引用它们的页面
+    pub fn broken_links(&self, task_id: u32) -> rusqlite::Result<Vec<BrokenLink>> {
+        report::broken_links(&self.conn.lock().unwrap(), task_id)
+    }
+
+    /// 统计任务的抓取成功、失败、跳过和待抓取的 URL 数
+    pub fn count_progress(&self, task_id: u32) -> rusqlite::Result<(u32, u32, u32, u32)> {
+        let conn = self.conn.lock().unwrap();
+        let count = |table: &str| {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {} WHERE task_id = ?1", table), params![task_id], |row| row.get(0))
+        };
+        Ok((count("fetch_results")?, count("errors")?, count("skipped")?, count("frontier")?))
+    }
+
+    pub fn record_error(&self, task_id: u32, url: &str, message: &str) -> rusqlite::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO errors (task_id, url, message, occurred_at) VALUES (?1, ?2, ?3, ?4)",
+            params![task_id, url, message, Utc::now().to_rfc3339()],
+        )?;
+        remove_from_frontier(&tx, task_id, url)?;
+        tx.commit()
+    }
+
+    /// 清空任务的抓取记录，并用种子重新填充待抓取队列
+    pub fn reset_task(&self, task_id: u32) -> rusqlite::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for table in ["frontier", "visited", "fetch_results", "errors", "skipped", "fingerprints", "links", "link_checks"] {
+            tx.execute(&format!("DELETE FROM {} WHERE task_id = ?1", table), params![task_id])?;
+        }
+        insert_seeds_into_frontier(&tx, task_id)?;
+        tx.commit()
+    }
+
+    /// 把任务的全部记录导出到一个独立的 SQLite 文件中
+    pub fn export_task(&self, task_id: u32, path: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("ATTACH DATABASE ?1 AS archive", params![path])?;
+        let result = (|| {
+            conn.execute("CREATE TABLE archive.tasks AS SELECT * FROM main.tasks WHERE id = ?1", params![task_id])?;
+            for (table, _) in TASK_TABLES {
+                conn.execute(
+                    &format!("CREATE TABLE archive.{table} AS SELECT * FROM main.{table} WHERE task_id = ?1"),
+                    params![task_id],
+                )?;
+            }
+            Ok(())
+        })();
+        conn.execute_batch("DETACH DATABASE archive")?;
+        result
+    }
+
+    /// 从 `export_task` 导出的文件导入任务，使用新分配的 ID 和指定的任务名，返回导入后的任务信息
+    pub fn import_task(&self, path: &str, name: &str) -> rusqlite::Result<TaskInfo> {
+        let mut conn = self.conn.lock().unwrap();
+        conn.execute("ATTACH DATABASE ?1 AS archive", params![path])?;
+        let result = (|| {
+            let tx = conn.transaction()?;
+            let (old_id, mut task_info, config): (u32, TaskInfo, String) =
+                tx.query_row("SELECT id, info, config FROM archive.tasks LIMIT 1", [], |row| {
+                    Ok((row.get(0)?, from_json(&row.get::<_, String>(1)?)?, row.get(2)?))
+                })?;
+            task_info.id = allocate_task_id(&tx)?;
+            task_info.name = name.to_string();
+            // 归档时仍在运行或暂停的任务导入后视为已停止
+            if matches!(task_info.status, TaskStatus::Running | TaskStatus::Paused) {
+                task_info.status = TaskStatus::Stopped;
+            }
+            tx.execute(
+                "INSERT INTO main.tasks (id, name, info, config) VALUES (?1, ?2, ?3, ?4)",
+                params![task_info.id, task_info.name, to_json(&task_info), config],
+            )?;
+            for (table, columns) in TASK_TABLES {
+                // 旧版本的归档中没有运行记录和页面版本表
+                if !archived_table(&tx, table)? {
+                    continue;
+                }
+                tx.execute(
+                    &format!("INSERT INTO main.{table} (task_id, {columns}) SELECT ?1, {columns} FROM archive.{table} WHERE task_id = ?2"),
+                    params![task_info.id, old_id],
+                )?;
+            }
+            // 运行记录导入后分配了新的 ID，按开始时间把页面版本对应到新的运行
+            if archived_table(&tx, "page_versions")? {
+                tx.execute(
+                    "UPDATE main.page_versions SET run_id = (
+                         SELECT r.id FROM main.runs r JOIN archive.runs a ON a.started_at = r.started_at
+                         WHERE r.task_id = ?1 AND a.task_id = ?2 AND a.id = page_versions.run_id
+                     ) WHERE task_id = ?1",
+                    params![task_info.id, old_id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(task_info)
+        })();
+        conn.execute_batch("DETACH DATABASE archive")?;
+        result
+    }
+
+    /// 汇总任务报告，任务不存在时返回 None
+    pub fn report(&self, task_id: u32) -> rusqlite::Result<Option<TaskReport>> {
+        let Some((task_info, config)) = self.load_task(task_id)? else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock().unwrap();
+        report::build(&conn, task_info, config).map(Some)
+    }
+
+    /// 生成任务当前抓取状态的检查点，同时把 WAL 中的内容合并回数据库文件
+    pub fn checkpoint(&self, task_id: u32) -> rusqlite::Result<Option<Checkpoint>> {
+        let Some((task_info, config)) = self.load_task(task_id)? else {
+            return Ok(None);
+        };
+        let checkpoint = Checkpoint {
+            task_info,
+            config,
+            seeds: self.load_seeds(task_id)?,
+            frontier: self.load_frontier(task_id)?,
+            visited: self.load_visited(task_id)?.into_iter().collect(),
+            saved_at: Utc::now().to_rfc3339(),
+        };
+        self.conn.lock().unwrap().execute_batch("PRAGMA wal_checkpoint(PASSIVE);")?;
+        Ok(Some(checkpoint))
+    }
+
+    /// 用检查点覆盖任务的抓取状态，已有的抓取结果和错误记录保持不变
+    pub fn restore(&self, checkpoint: &Checkpoint) -> rusqlite::Result<()> {
+        let task_id = checkpoint.task_info.id;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO tasks (id, name, info, config) VALUES (?1, ?2, ?3, ?4)",
+            params![task_id, checkpoint.task_info.name, to_json(&checkpoint.task_info), to_json(&checkpoint.config)],
+        )?;
+        for table in ["seeds", "frontier", "visited"] {
+            tx.execute(&format!("DELETE FROM {} WHERE task_id = ?1", table), params![task_id])?;
+        }
+        for url in &checkpoint.seeds {
+            tx.execute("INSERT OR IGNORE INTO seeds (task_id, url) VALUES (?1, ?2)", params![task_id, url])?;
+        }
+        for (url, depth) in &checkpoint.frontier {
+            tx.execute("INSERT OR IGNORE INTO frontier (task_id, url, depth) VALUES (?1, ?2, ?3)", params![task_id, url, depth])?;
+        }
+        for url in &checkpoint.visited {
+            tx.execute("INSERT OR IGNORE INTO visited (task_id, url) VALUES (?1, ?2)", params![task_id, url])?;
+        }
+        tx.commit()
+    }
+}
+
+fn open_connection(path: &str) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+    conn.execute_batch(SCHEMA)?;
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+// 为旧版本创建的数据库补充新增的列和索引
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    if conn.prepare("SELECT elapsed_ms FROM fetch_results LIMIT 0").is_err() {
+        conn.execute_batch("ALTER TABLE fetch_results ADD COLUMN elapsed_ms INTEGER NOT NULL DEFAULT 0")?;
+    }
+    let unique_names: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'tasks_name_unique')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !unique_names {
+        rename_duplicate_tasks(conn)?;
+        conn.execute_batch("DROP INDEX IF EXISTS tasks_name; CREATE UNIQUE INDEX tasks_name_unique ON tasks (name);")?;
+    }
+    Ok(())
+}
+
+// 旧版本允许重名任务：保留最新创建的一个，其余的任务名加上 `-<ID>` 后缀
+fn rename_duplicate_tasks(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare("SELECT id, info FROM tasks WHERE id NOT IN (SELECT MAX(id) FROM tasks GROUP BY name)")?;
+    let duplicates = stmt
+        .query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (task_id, info) in duplicates {
+        let mut task_info: TaskInfo = from_json(&info)?;
+        task_info.name = format!("{}-{}", task_info.name, task_id);
+        warn!("Renaming task {} with a duplicate name to {}", task_id, task_info.name);
+        conn.execute("UPDATE tasks SET name = ?2, info = ?3 WHERE id = ?1", params![task_id, task_info.name, to_json(&task_info)])?;
+    }
+    Ok(())
+}
+
+// 删除任务及其所有记录，由调用方提交事务
+fn delete_task_rows(conn: &Connection, task_id: u32) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM tasks WHERE id = ?1", params![task_id])?;
+    for (table, _) in TASK_TABLES {
+        conn.execute(&format!("DELETE FROM {} WHERE task_id = ?1", table), params![task_id])?;
+    }
+    Ok(())
+}
+
+pub(super) fn load_runs(conn: &Connection, task_id: u32, limit: Option<usize>) -> rusqlite::Result<Vec<RunRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, triggered_by, status, started_at, ended_at, stats FROM runs WHERE task_id = ?1 ORDER BY id DESC LIMIT ?2",
+    )?;
+    let runs = stmt
+        .query_map(params![task_id, limit.map_or(-1, |limit| limit as i64)], |row| {
+            Ok(RunRecord {
+                id: row.get(0)?,
+                triggered_by: row.get(1)?,
+                status: row.get(2)?,
+                started_at: row.get(3)?,
+                ended_at: row.get(4)?,
+                stats: row.get::<_, Option<String>>(5)?.map(|stats| from_json(&stats)).transpose()?,
+            })
+        })?
+        .collect();
+    runs
+}
+
+// 计划的下一次运行时间，从最近一次运行开始的时间（没有运行记录时为任务创建时间）之后计算
+pub(super) fn next_run(conn: &Connection, task_info: &TaskInfo, config: &TaskConfig) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    if config.schedule.cron.is_none() {
+        return Ok(None);
+    }
+    let last_started: Option<String> = conn
+        .query_row("SELECT started_at FROM runs WHERE task_id = ?1 ORDER BY id DESC LIMIT 1", params![task_info.id], |row| row.get(0))
+        .optional()?;
+    let after = last_started.as_deref()
+        .or(task_info.created_at.as_deref())
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map_or_else(Utc::now, |time| time.with_timezone(&Utc));
+    Ok(config.schedule.next_run(after))
+}
+
+// 导入的归档中是否有这张表
+fn archived_table(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM archive.sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )
+}
+
+fn is_corrupt(e: &rusqlite::Error) -> bool {
+    matches!(e.sqlite_error_code(), Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase))
+}
+
+fn remove_from_frontier(conn: &Connection, task_id: u32, url: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM frontier WHERE task_id = ?1 AND url = ?2", params![task_id, url])?;
+    Ok(())
+}
+
+// 分配新的任务 ID；记录已分配的最大 ID，删除任务后其 ID 也不会被重新使用
+fn allocate_task_id(conn: &Connection) -> rusqlite::Result<u32> {
+    let task_id: u32 = conn.query_row(
+        "SELECT MAX(COALESCE((SELECT MAX(id) FROM main.tasks), 0), COALESCE((SELECT value FROM main.meta WHERE key = 'last_task_id'), 0)) + 1",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO main.meta (key, value) VALUES ('last_task_id', ?1) ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        params![task_id],
+    )?;
+    Ok(task_id)
+}
+
+fn insert_seeds_into_frontier(conn: &Connection, task_id: u32) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR IGNORE INTO frontier (task_id, url, depth) SELECT task_id, url, 1 FROM seeds WHERE task_id = ?1",
+        params![task_id],
+    )
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("Failed to serialize task data")
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(value: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(value).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(store: &TaskStore, name: &str, replace: bool) -> CreateOutcome {
+        let seeds = ["http://a.com/".to_string()];
+        store.create_task(&TaskInfo::new(name.to_string(), 2), &TaskConfig::default(), &seeds, replace).unwrap()
+    }
+
+    fn created_id(outcome: CreateOutcome) -> u32 {
+        match outcome {
+            CreateOutcome::Created { id, .. } => id,
+            CreateOutcome::NameTaken(id) => panic!("name taken by task {}", id),
+        }
+    }
+
+    #[test]
+    fn validates_task_names() {
+        for name in ["docs", "docs-2024", "文档", "a.b"] {
+            assert!(validate_task_name(name).is_ok(), "{:?} should be valid", name);
+        }
+        for name in ["", ".", "..", ".hidden", "a/b", "../x", "a\\b"] {
+            assert!(validate_task_name(name).is_err(), "{:?} should be rejected", name);
+        }
+    }
+
+    #[test]
+    fn creates_and_resolves_tasks() {
+        let store = TaskStore::open(":memory:").unwrap();
+        let id = created_id(create(&store, "docs", false));
+        assert_eq!(store.resolve(&TaskRef::Id(id)).unwrap(), Some(id));
+        assert_eq!(store.resolve(&TaskRef::Name("docs".to_string())).unwrap(), Some(id));
+        assert_eq!(store.resolve(&TaskRef::IdOrName(id.to_string())).unwrap(), Some(id));
+        assert_eq!(store.resolve(&TaskRef::IdOrName("docs".to_string())).unwrap(), Some(id));
+        assert_eq!(store.resolve(&TaskRef::Name("missing".to_string())).unwrap(), None);
+        let (task_info, _) = store.load_task(id).unwrap().unwrap();
+        assert_eq!((task_info.id, task_info.name.as_str(), task_info.deep), (id, "docs", 2));
+    }
+
+    #[test]
+    fn numeric_names_resolve_when_no_task_has_that_id() {
+        let store = TaskStore::open(":memory:").unwrap();
+        let id = created_id(create(&store, "2024", false));
+        assert_ne!(id, 2024);
+        assert_eq!(store.resolve(&TaskRef::IdOrName("2024".to_string())).unwrap(), Some(id));
+    }
+
+    #[test]
+    fn names_are_unique_unless_replaced() {
+        let store = TaskStore::open(":memory:").unwrap();
+        let first = created_id(create(&store, "docs", false));
+        assert!(matches!(create(&store, "docs", false), CreateOutcome::NameTaken(id) if id == first));
+        match create(&store, "docs", true) {
+            CreateOutcome::Created { id, replaced } => {
+                assert_eq!(replaced, Some(first));
+                assert_eq!(store.resolve(&TaskRef::Name("docs".to_string())).unwrap(), Some(id));
+                assert_eq!(store.load_task(first).unwrap().map(|(task_info, _)| task_info.id), None);
+            }
+            CreateOutcome::NameTaken(_) => panic!("replace should delete the existing task"),
+        }
+        assert_eq!(store.list_tasks().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn deletes_tasks() {
+        let store = TaskStore::open(":memory:").unwrap();
+        let docs = created_id(create(&store, "docs", false));
+        let blog = created_id(create(&store, "blog", false));
+        store.delete_task(docs).unwrap();
+        assert_eq!(store.resolve(&TaskRef::Id(docs)).unwrap(), None);
+        assert!(store.load_task(docs).unwrap().is_none());
+        assert_eq!(store.resolve(&TaskRef::Name("blog".to_string())).unwrap(), Some(blog));
+        // 删除后可以重新使用任务名
+        created_id(create(&store, "docs", false));
+    }
+}
@@ -0,0 +1,15 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// 先写入同目录下的临时文件并刷盘，再重命名覆盖目标文件，保证目标文件要么是旧内容要么是完整的新内容
+pub fn atomic_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
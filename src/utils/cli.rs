@@ -1,4 +1,9 @@
-use clap::{Arg, ArgMatches, Command};
+use std::net::SocketAddr;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{Arg, ArgGroup, ArgMatches, Command};
+use crate::store::{GraphFormat, TaskRef};
+use crate::worker::{BudgetConfig, ListOptions, ListSort, OutputFormat, TaskStatus};
+use crate::worker::schedule::parse_cron;
 
 /// 解析命令行参数的函数。
 pub fn parse_args() -> (ArgMatches, Command<'static>) {
@@ -6,63 +11,283 @@ pub fn parse_args() -> (ArgMatches, Command<'static>) {
         .version("1.0")
         .author("Gao Yuan <yuan.gao@13un.com>")
         .about("A simple web crawler")
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .takes_value(true)
+                .global(true)
+                .help("The log level or RUST_LOG style filter (e.g. debug, spider=debug,reqwest=warn)"),
+        )
+        .arg(
+            Arg::new("log-json")
+                .long("log-json")
+                .global(true)
+                .help("Write the task log file as JSON lines"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .global(true)
+                .help("Only print fatal errors, logs still go to the task log file"),
+        )
+        .arg(
+            Arg::new("no-progress")
+                .long("no-progress")
+                .global(true)
+                .help("Print plain status lines instead of progress bars (default when not on a terminal)"),
+        )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .global(true)
+                .conflicts_with_all(&["quiet", "no-progress"])
+                .help("Show a full screen dashboard while the task runs (p pause, r resume, +/- concurrency, q quit)"),
+        )
+        .arg(
+            Arg::new("metrics-addr")
+                .long("metrics-addr")
+                .takes_value(true)
+                .value_name("addr")
+                .global(true)
+                .value_parser(clap::value_parser!(SocketAddr))
+                .help("Serve Prometheus metrics of the running task on this address (e.g. 127.0.0.1:9898)"),
+        )
+        .subcommand(budget_args(create_args(
+            Command::new("create").about("Creates a new crawling task"),
+        )))
+        .subcommand(budget_args(create_args(
+            Command::new("check-links")
+                .about("Crawls the seed hosts and reports broken links, checking links to other hosts without following them")
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .takes_value(true)
+                        .value_name("secs")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .help("Seconds to wait for each request before reporting the link as broken [default: 10]"),
+                ),
+        )))
         .subcommand(
-            Command::new("create")
-                .about("Creates a new crawling task")
+            Command::new("list")
+                .about("Lists all crawling tasks")
+                .arg(
+                    Arg::new("status")
+                        .long("status")
+                        .takes_value(true)
+                        .value_parser(["idle", "running", "paused", "finished", "stopped"])
+                        .help("Only list tasks with this status"),
+                )
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .takes_value(true)
+                        .value_parser(parse_since)
+                        .help("Only list tasks created since a date (2024-01-31), an RFC 3339 time or a duration ago (30m, 12h, 7d)"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .takes_value(true)
+                        .default_value("created")
+                        .value_parser(["created", "name", "progress"])
+                        .help("The field to sort tasks by"),
+                )
+                .arg(
+                    Arg::new("reverse")
+                        .long("reverse")
+                        .help("Reverse the sort order"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .takes_value(true)
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The maximum number of tasks to list"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .takes_value(true)
+                        .default_value("table")
+                        .value_parser(["table", "json", "csv"])
+                        .help("The output format"),
+                ),
+        )
+        .subcommand(task_args(
+            Command::new("show")
+                .about("Shows a detailed report of a crawling task")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the report as JSON"),
+                )
+        ))
+        .subcommand(task_args(
+            Command::new("pause")
+                .about("Pauses a running crawling task without stopping the process")
+        ))
+        .subcommand(budget_args(task_args(
+            Command::new("resume")
+                .about("Resumes a stopped or paused crawling task")
+        )))
+        .subcommand(budget_args(task_args(
+            Command::new("restart")
+                .about("Restarts a crawling task")
+        )))
+        .subcommand(task_args(
+            Command::new("schedule")
+                .about("Sets or clears the cron schedule of a crawling task")
+                .arg(
+                    Arg::new("cron")
+                        .long("cron")
+                        .takes_value(true)
+                        .value_name("cron")
+                        .value_parser(parse_schedule)
+                        .help("The cron schedule in local time (minute hour day month weekday, e.g. \"0 3 * * *\", @daily)"),
+                )
+                .arg(
+                    Arg::new("clear")
+                        .long("clear")
+                        .help("Remove the schedule"),
+                )
+                .group(ArgGroup::new("schedule").args(&["cron", "clear"]).required(true))
+        ))
+        .subcommand(task_args(
+            Command::new("diff")
+                .about("Lists new, removed and modified pages between two runs of a crawling task")
+                .arg(
+                    Arg::new("run")
+                        .long("run")
+                        .takes_value(true)
+                        .value_name("id")
+                        .multiple_occurrences(true)
+                        .max_occurrences(2)
+                        .value_parser(clap::value_parser!(u32))
+                        .help("A run to compare, given once to compare with the run before it or twice (older first); defaults to the last two runs"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the changes as JSON"),
+                )
+        ))
+        .subcommand(task_args(
+            Command::new("export-graph")
+                .about("Exports the link graph of a crawling task with PageRank and in-degree per page")
                 .arg(
-                    Arg::new("file")
+                    Arg::new("format")
                         .short('f')
-                        .long("file")
+                        .long("format")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_parser(["csv", "graphml", "dot"])
+                        .help("The format to write, can be given more than once (defaults to all formats)"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("dir")
+                        .help("The directory to write to (defaults to tasks/<name>/graph)"),
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .takes_value(true)
+                        .default_value("10")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Print this many pages with the highest PageRank, 0 to print none"),
+                )
+        ))
+        .subcommand(task_args(
+            Command::new("delete")
+                .about("Deletes a crawling task and its task directory")
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Delete without asking for confirmation"),
+                )
+        ))
+        .subcommand(task_args(
+            Command::new("archive")
+                .about("Packs a crawling task into a compressed tarball")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .help("The archive file to write (defaults to <name>-<id>.tar.gz)"),
+                )
+                .arg(
+                    Arg::new("delete")
+                        .long("delete")
+                        .help("Delete the task after it has been archived"),
+                )
+        ))
+        .subcommand(
+            Command::new("import")
+                .about("Imports a crawling task from an archive")
+                .arg(
+                    Arg::new("archive")
                         .takes_value(true)
                         .required(true)
-                        .help("The file containing a list of URLs to crawl"),
+                        .help("The archive file created by the archive command"),
                 )
                 .arg(
                     Arg::new("name")
                         .short('n')
                         .long("name")
                         .takes_value(true)
-                        .required(true)
-                        .help("The name of the crawling task"),
+                        .help("Import the task under a different name"),
                 )
                 .arg(
-                    Arg::new("deep")
-                        .short('d')
-                        .long("deep")
-                        .takes_value(true)
-                        .required(true)
-                        .value_parser(clap::value_parser!(u32))
-                        .help("The deep of the spider cycle"),
+                    Arg::new("force")
+                        .long("force")
+                        .help("Replace an existing task with the same name"),
                 ),
         )
         .subcommand(
-            Command::new("list")
-                .about("Lists all crawling tasks")
-        )
-        .subcommand(
-            Command::new("resume")
-                .about("Resumes a crawling task")
+            Command::new("serve")
+                .about("Runs a local HTTP API to create, run and control tasks")
                 .arg(
-                    Arg::new("id")
-                        .short('i')
-                        .long("id")
+                    Arg::new("addr")
+                        .long("addr")
                         .takes_value(true)
-                        .required(true)
-                        .value_parser(clap::value_parser!(u32))
-                        .help("The ID of the task to resume"),
+                        .value_name("addr")
+                        .default_value("127.0.0.1:7878")
+                        .value_parser(clap::value_parser!(SocketAddr))
+                        .help("The address to listen on, the API has no authentication"),
                 ),
         )
         .subcommand(
-            Command::new("restart")
-                .about("Restarts a crawling task")
+            Command::new("bench")
+                .about("Benchmarks the thread and async engines against a local test server")
                 .arg(
-                    Arg::new("id")
-                        .short('i')
-                        .long("id")
+                    Arg::new("pages")
+                        .long("pages")
                         .takes_value(true)
-                        .required(true)
-                        .value_parser(clap::value_parser!(u32))
-                        .help("The ID of the task to restart"),
+                        .default_value("2000")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The number of pages to fetch"),
+                )
+                .arg(
+                    Arg::new("latency")
+                        .long("latency")
+                        .takes_value(true)
+                        .default_value("50")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("The simulated response latency in milliseconds"),
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .long("concurrency")
+                        .takes_value(true)
+                        .default_value("256")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The maximum number of in-flight requests of the async engine"),
                 ),
         );
 
@@ -70,3 +295,291 @@ pub fn parse_args() -> (ArgMatches, Command<'static>) {
 
     (matches, app)
 }
+
+/// 从 list 子命令参数中取出过滤、排序和输出选项
+pub fn list_options(matches: &ArgMatches) -> ListOptions {
+    ListOptions {
+        status: matches.get_one::<String>("status").map(|status| status.parse::<TaskStatus>().unwrap()),
+        since: matches.get_one::<DateTime<Utc>>("since").copied(),
+        sort: match matches.get_one::<String>("sort").unwrap().as_str() {
+            "name" => ListSort::Name,
+            "progress" => ListSort::Progress,
+            _ => ListSort::Created,
+        },
+        reverse: matches.is_present("reverse"),
+        limit: matches.get_one::<usize>("limit").copied(),
+        format: match matches.get_one::<String>("format").unwrap().as_str() {
+            "json" => OutputFormat::Json,
+            "csv" => OutputFormat::Csv,
+            _ => OutputFormat::Table,
+        },
+    }
+}
+
+/// 从 export-graph 子命令参数中取出输出格式，未指定时导出全部格式
+pub fn graph_formats(matches: &ArgMatches) -> Vec<GraphFormat> {
+    let Some(formats) = matches.get_many::<String>("format") else {
+        return vec![GraphFormat::Csv, GraphFormat::GraphMl, GraphFormat::Dot];
+    };
+    formats
+        .map(|format| match format.as_str() {
+            "graphml" => GraphFormat::GraphMl,
+            "dot" => GraphFormat::Dot,
+            _ => GraphFormat::Csv,
+        })
+        .collect()
+}
+
+/// 解析 --since：日期、RFC 3339 时间，或以 m/h/d 结尾的时长（表示距今多久之前）
+pub(crate) fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    // 按字符取单位，输入以多字节字符结尾时不会在字符中间切分
+    let invalid = || format!("invalid time {}", value);
+    let (amount, to_duration): (&str, fn(i64) -> Option<Duration>) = if let Some(amount) = value.strip_suffix('m') {
+        (amount, Duration::try_minutes)
+    } else if let Some(amount) = value.strip_suffix('h') {
+        (amount, Duration::try_hours)
+    } else if let Some(amount) = value.strip_suffix('d') {
+        (amount, Duration::try_days)
+    } else {
+        return Err(invalid());
+    };
+    // 只接受非负数，负数会得到未来的时间
+    let amount: u32 = amount.parse().map_err(|_| invalid())?;
+    to_duration(amount.into())
+        .and_then(|duration| Utc::now().checked_sub_signed(duration))
+        .ok_or_else(|| format!("time {} is out of range", value))
+}
+
+// 校验 cron 表达式
+fn parse_schedule(value: &str) -> Result<String, String> {
+    parse_cron(value).map(|_| value.to_string())
+}
+
+// 创建任务的参数，create 和 check-links 共用
+fn create_args(command: Command<'static>) -> Command<'static> {
+    command
+        .arg(
+            Arg::new("file")
+                .short('f')
+                .long("file")
+                .takes_value(true)
+                .required(true)
+                .help("The file containing a list of URLs to crawl"),
+        )
+        .arg(
+            Arg::new("name")
+                .short('n')
+                .long("name")
+                .takes_value(true)
+                .required(true)
+                .help("The name of the crawling task"),
+        )
+        .arg(
+            Arg::new("deep")
+                .short('d')
+                .long("deep")
+                .takes_value(true)
+                .required(true)
+                .value_parser(clap::value_parser!(u32))
+                .help("The deep of the spider cycle"),
+        )
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .takes_value(true)
+                .help("The TOML file containing the task config (e.g. authentication)"),
+        )
+        .arg(
+            Arg::new("schedule")
+                .long("schedule")
+                .takes_value(true)
+                .value_name("cron")
+                .value_parser(parse_schedule)
+                .help("Run the task again on this cron schedule under spider serve (e.g. \"0 3 * * *\", @daily)"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .help("Replace an existing task with the same name"),
+        )
+}
+
+/// 为操作单个任务的子命令添加任务引用参数：位置参数可以是 ID 或任务名，也可以用 --id / --name 明确指定
+fn task_args(command: Command<'static>) -> Command<'static> {
+    command
+        .arg(
+            Arg::new("task")
+                .takes_value(true)
+                .help("The ID or name of the task"),
+        )
+        .arg(
+            Arg::new("id")
+                .short('i')
+                .long("id")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u32))
+                .help("The ID of the task"),
+        )
+        .arg(
+            Arg::new("name")
+                .short('n')
+                .long("name")
+                .takes_value(true)
+                .help("The name of the task"),
+        )
+        .group(ArgGroup::new("task_ref").args(&["task", "id", "name"]).required(true))
+}
+
+/// 从子命令参数中取出任务引用
+pub fn task_ref(matches: &ArgMatches) -> TaskRef {
+    if let Some(id) = matches.get_one::<u32>("id") {
+        TaskRef::Id(*id)
+    } else if let Some(name) = matches.get_one::<String>("name") {
+        TaskRef::Name(name.clone())
+    } else {
+        TaskRef::IdOrName(matches.get_one::<String>("task").unwrap().clone())
+    }
+}
+
+/// 为运行任务的子命令添加抓取预算参数，设置的项覆盖任务配置中的预算
+fn budget_args(command: Command<'static>) -> Command<'static> {
+    command
+        .arg(
+            Arg::new("max-pages")
+                .long("max-pages")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u32))
+                .help("Stop after fetching this many pages in total"),
+        )
+        .arg(
+            Arg::new("max-bytes")
+                .long("max-bytes")
+                .takes_value(true)
+                .value_parser(parse_size)
+                .help("Stop after downloading this many bytes in total (e.g. 500M, 2G)"),
+        )
+        .arg(
+            Arg::new("max-duration")
+                .long("max-duration")
+                .takes_value(true)
+                .value_parser(parse_duration)
+                .help("Stop scheduling new fetches after running this long (e.g. 90s, 30m, 2h)"),
+        )
+        .arg(
+            Arg::new("max-pages-per-host")
+                .long("max-pages-per-host")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u32))
+                .help("Fetch at most this many pages from each host"),
+        )
+}
+
+/// 从子命令参数中取出抓取预算，未指定的项为 None
+pub fn budget_overrides(matches: &ArgMatches) -> BudgetConfig {
+    BudgetConfig {
+        max_pages: matches.get_one::<u32>("max-pages").copied(),
+        max_bytes: matches.get_one::<u64>("max-bytes").copied(),
+        max_duration_secs: matches.get_one::<u64>("max-duration").copied(),
+        max_pages_per_host: matches.get_one::<u32>("max-pages-per-host").copied(),
+        ..BudgetConfig::default()
+    }
+}
+
+// 解析字节数，可以带 K/M/G/T 后缀（1024 进制）
+fn parse_size(value: &str) -> Result<u64, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (amount, multiplier) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 1u64 << 10),
+        Some('M') => (&number[..number.len() - 1], 1 << 20),
+        Some('G') => (&number[..number.len() - 1], 1 << 30),
+        Some('T') => (&number[..number.len() - 1], 1 << 40),
+        _ => (number, 1),
+    };
+    let amount: u64 = amount.trim().parse().map_err(|_| format!("invalid size {}", value))?;
+    amount.checked_mul(multiplier).ok_or_else(|| format!("size {} is too large", value))
+}
+
+// 解析时长，单位为 s/m/h/d，不带单位时为秒
+fn parse_duration(value: &str) -> Result<u64, String> {
+    let (amount, multiplier) = match value.chars().last() {
+        Some('s') => (&value[..value.len() - 1], 1u64),
+        Some('m') => (&value[..value.len() - 1], 60),
+        Some('h') => (&value[..value.len() - 1], 60 * 60),
+        Some('d') => (&value[..value.len() - 1], 24 * 60 * 60),
+        _ => (value, 1),
+    };
+    let amount: u64 = amount.parse().map_err(|_| format!("invalid duration {}", value))?;
+    amount.checked_mul(multiplier).ok_or_else(|| format!("duration {} is too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10k"), Ok(10 << 10));
+        assert_eq!(parse_size("5MB"), Ok(5 << 20));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_size(" 1T "), Ok(1 << 40));
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("abc").is_err());
+        assert!(parse_size("-1M").is_err());
+        assert!(parse_size("5兆").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration("30s"), Ok(30));
+        assert_eq!(parse_duration("5m"), Ok(300));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+        assert_eq!(parse_duration("1d"), Ok(86400));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5秒").is_err());
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn parses_absolute_since() {
+        assert_eq!(parse_since("2024-05-01").unwrap().to_rfc3339(), "2024-05-01T00:00:00+00:00");
+        assert_eq!(parse_since("2024-05-01T08:30:00+08:00").unwrap().to_rfc3339(), "2024-05-01T00:30:00+00:00");
+    }
+
+    #[test]
+    fn parses_relative_since() {
+        let since = parse_since("2h").unwrap();
+        let elapsed = Utc::now() - since;
+        assert!(elapsed >= Duration::hours(2) && elapsed < Duration::hours(2) + Duration::minutes(1));
+        assert!(parse_since("30m").unwrap() > parse_since("1d").unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_since() {
+        for value in ["", "d", "7", "7天", "天", "7w", "yesterday", "-", "-5d", "-0m"] {
+            assert!(parse_since(value).is_err(), "{:?} should be rejected", value);
+        }
+        assert!(parse_since("9999999999999d").is_err());
+        assert!(parse_since("9223372036854775807m").is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::error::Error;
+use std::fs;
+use serde::{Serialize, Deserialize};
+use crate::fetcher::{AuthConfig, CacheConfig, DownloadConfig};
+use crate::store::CheckpointConfig;
+use crate::worker::{BudgetConfig, DedupConfig, EngineConfig, LinkCheckConfig, ScheduleConfig};
+
+/// 任务配置，通过 `create --config <file.toml>` 指定，随任务一起保存在任务存储中
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct TaskConfig {
+    #[serde(default)]
+    pub engine: EngineConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
+    pub checkpoint: CheckpointConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub check_links: LinkCheckConfig,
+}
+
+impl TaskConfig {
+    /// 从 TOML 文件加载任务配置
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let config = toml::from_str(&content)?;
+        Ok(config)
+    }
+
+    /// 检查配置中无法在解析时发现的错误，例如无效的 cron 表达式
+    pub fn validate(&self) -> Result<(), String> {
+        self.schedule.validate()?;
+        self.dedup.validate()
+    }
+
+    /// 隐去认证信息的副本，用于展示配置
+    pub fn redacted(&self) -> Self {
+        TaskConfig { auth: self.auth.redacted(), ..self.clone() }
+    }
+}
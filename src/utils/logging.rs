@@ -1,82 +1,249 @@
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Sender};
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::sync::{Mutex, OnceLock};
 use std::thread;
+use chrono::{Local, Utc};
+use env_logger::filter::{Builder, Filter};
 use indicatif::{ProgressBar, MultiProgress};
 use indicatif::ProgressStyle;
-use crate::MULTI_PROGRESS;
-use std::collections::VecDeque;
-use crate::TERMINATE;
+use log::{Log, Metadata, Record};
+use serde_json::json;
+use super::output::OutputMode;
 
 const MAX_LOG_LINES: usize = 20; // 定长队列的最大行数
+// 仪表盘中保留的最近警告和错误行数
+const MAX_RECENT_ERRORS: usize = 50;
 
-pub struct Logging {
-    log_lines: Arc<Mutex<VecDeque<String>>>,
-    tx: Sender<String>,
-    pb: ProgressBar,
+/// 任务日志文件名，保存在任务目录中
+pub const LOG_FILE: &str = "spider.log";
+// 日志文件超过该大小后轮转为 spider.log.1、spider.log.2 ……
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+// 最多保留的轮转日志文件数
+const MAX_LOG_FILES: usize = 5;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+// 当前线程正在运行的任务，用于把日志写入该任务的日志文件
+thread_local! {
+    static CURRENT_TASK: Cell<Option<u32>> = const { Cell::new(None) };
 }
 
-impl Logging {
-    pub fn new(m: &MultiProgress) -> Self {
-        let (tx, rx) = channel();
-        let log_lines = Arc::new(Mutex::new(VecDeque::with_capacity(MAX_LOG_LINES)));
-        // 创建一个用于显示日志信息的进度条
-        let pb = m.add(ProgressBar::new_spinner());
-        pb.set_style(ProgressStyle::default_spinner().template("{msg}").unwrap());
-
-        let log_lines_clone = Arc::clone(&log_lines);
-        let mut pb_clone = pb.clone();
-        // 使用 Arc<Mutex<...>> 来共享 log_lines
-        thread::spawn(move || {
-            // while !TERMINATE.load(Ordering::SeqCst) {
-                while let Ok(message) = rx.recv() {
-                    let mut log_lines = log_lines_clone.lock().unwrap();
-                    if log_lines.len() == MAX_LOG_LINES {
-                        log_lines.pop_front();
-                    }
-                    log_lines.push_back(message);
-                    
-                    // 将最新的日志内容拼接成一个大字符串
-                    let log_message = log_lines.iter().cloned().collect::<Vec<_>>().join("\n");
-                    pb_clone.set_message(log_message);
-                }
-            // }
-        });
+// 异步引擎的请求在 tokio 线程池中执行，通过 task-local 标记所属任务
+tokio::task_local! {
+    static TASK_SCOPE: u32;
+}
 
-        Logging { log_lines, tx, pb }
-    }
+/// 日志输出选项，来自命令行参数和 `RUST_LOG` 环境变量
+pub struct LogOptions {
+    /// 日志级别或 `RUST_LOG` 格式的过滤规则，例如 `debug` 或 `spider=debug,reqwest=warn`
+    pub level: Option<String>,
+    /// 日志文件中每行输出一个 JSON 对象
+    pub json: bool,
+    /// 屏幕上的日志输出方式
+    pub mode: OutputMode,
+}
+
+/// `log` crate 的日志后端：按级别过滤后输出到屏幕和运行中任务的日志文件
+struct Logger {
+    filter: Filter,
+    json: bool,
+    mode: OutputMode,
+    // 交互式终端上的日志面板
+    screen: Option<Sender<String>>,
+    // 仪表盘模式下最近的警告和错误
+    recent: Mutex<VecDeque<String>>,
+    // 运行中任务的日志文件，按任务 ID 区分
+    files: Mutex<HashMap<u32, TaskLog>>,
+}
+
+// 运行中任务的日志文件
+struct TaskLog {
+    task_id: u32,
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+/// 初始化日志后端；交互式终端上显示只保留最近日志行的日志面板，否则警告和错误直接写到 stderr
+pub fn init(m: &MultiProgress, options: LogOptions) {
+    // 命令行参数优先于 RUST_LOG，都没有时输出 info 及以上级别
+    let spec = options.level.or_else(|| std::env::var("RUST_LOG").ok()).unwrap_or_else(|| "info".to_string());
+    let filter = Builder::new().parse(&spec).build();
+    let max_level = filter.filter();
 
-    pub fn log(&self, level: &str, message: String) {
-        self.tx.send(format!("{}: {}", level, message)).unwrap();
+    let logger = LOGGER.get_or_init(|| Logger {
+        filter,
+        json: options.json,
+        mode: options.mode,
+        screen: (options.mode == OutputMode::Interactive).then(|| screen_tail(m)),
+        recent: Mutex::new(VecDeque::new()),
+        files: Mutex::new(HashMap::new()),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
     }
 }
 
-lazy_static! {
-    pub static ref LOGGER: Arc<Mutex<Logging>> = Arc::new(Mutex::new(Logging::new(&MULTI_PROGRESS)));
+/// 把任务之后的日志同时写入任务目录中的日志文件，文件过大时自动轮转；返回的 guard 释放时停止写入。
+/// 当前线程的日志属于该任务，其他线程中的日志需要用 `set_current_task` 或 `in_task` 标记所属任务
+pub fn open_task_log(task_id: u32, task_dir: &str) -> io::Result<TaskLogGuard> {
+    set_current_task(Some(task_id));
+    let Some(logger) = LOGGER.get() else {
+        return Ok(TaskLogGuard { task_id });
+    };
+    fs::create_dir_all(task_dir)?;
+    let path = Path::new(task_dir).join(LOG_FILE);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let size = file.metadata()?.len();
+    logger.files.lock().unwrap().insert(task_id, TaskLog { task_id, path, file, size });
+    Ok(TaskLogGuard { task_id })
+}
+
+/// 标记当前线程之后的日志属于哪个任务，worker 线程开始运行时调用
+pub fn set_current_task(task_id: Option<u32>) {
+    CURRENT_TASK.with(|current| current.set(task_id));
 }
 
-#[macro_export]
-macro_rules! info {
-    ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::utils::logging::LOGGER.lock() {
-            logger.log("INFO", format!($($arg)*));
+/// 标记异步请求中的日志属于哪个任务
+pub fn in_task<F: Future>(task_id: u32, future: F) -> impl Future<Output = F::Output> {
+    TASK_SCOPE.scope(task_id, future)
+}
+
+/// 最近的警告和错误日志，最新的在最后；只在仪表盘模式下记录
+pub fn recent_errors() -> Vec<String> {
+    LOGGER.get().map(|logger| logger.recent.lock().unwrap().iter().cloned().collect()).unwrap_or_default()
+}
+
+/// 任务日志文件的写入期间
+pub struct TaskLogGuard {
+    task_id: u32,
+}
+
+impl Drop for TaskLogGuard {
+    fn drop(&mut self) {
+        set_current_task(None);
+        if let Some(logger) = LOGGER.get() {
+            logger.files.lock().unwrap().remove(&self.task_id);
         }
-    };
+    }
 }
 
-#[macro_export]
-macro_rules! warn {
-    ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::utils::logging::LOGGER.lock() {
-            logger.log("WARN", format!($($arg)*));
+// 创建显示日志的面板，由后台线程接收日志行并刷新
+fn screen_tail(m: &MultiProgress) -> Sender<String> {
+    let (tx, rx) = channel::<String>();
+    // 创建一个用于显示日志信息的进度条
+    let pb = m.add(ProgressBar::new_spinner());
+    pb.set_style(ProgressStyle::default_spinner().template("{msg}").unwrap());
+
+    thread::spawn(move || {
+        let mut log_lines = VecDeque::with_capacity(MAX_LOG_LINES);
+        while let Ok(message) = rx.recv() {
+            if log_lines.len() == MAX_LOG_LINES {
+                log_lines.pop_front();
+            }
+            log_lines.push_back(message);
+
+            // 将最新的日志内容拼接成一个大字符串
+            let log_message = log_lines.iter().cloned().collect::<Vec<_>>().join("\n");
+            pb.set_message(log_message);
         }
-    };
+    });
+    tx
 }
 
-#[macro_export]
-macro_rules! error {
-    ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::utils::logging::LOGGER.lock() {
-            logger.log("ERROR", format!($($arg)*));
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
         }
-    };
+        let url = record.key_values().get(log::kv::Key::from("url")).map(|url| url.to_string());
+        match (&self.screen, self.mode) {
+            (Some(screen), _) => {
+                let _ = screen.send(format!("{}: {}", record.level(), record.args()));
+            }
+            (None, OutputMode::Plain) if record.level() <= log::Level::Warn => {
+                eprintln!("{}: {}", record.level(), record.args());
+            }
+            (None, OutputMode::Dashboard) if record.level() <= log::Level::Warn => {
+                let mut recent = self.recent.lock().unwrap();
+                if recent.len() == MAX_RECENT_ERRORS {
+                    recent.pop_front();
+                }
+                recent.push_back(format!("{} {}: {}", Local::now().format("%H:%M:%S"), record.level(), record.args()));
+            }
+            _ => {}
+        }
+
+        let mut files = self.files.lock().unwrap();
+        let task_id = TASK_SCOPE.try_with(|task_id| *task_id).ok().or_else(|| CURRENT_TASK.with(Cell::get));
+        let Some(task_log) = task_id.and_then(|task_id| files.get_mut(&task_id)) else {
+            return;
+        };
+        let time = Utc::now().to_rfc3339();
+        let thread = thread::current();
+        let thread = thread.name().map(str::to_string).unwrap_or_else(|| format!("{:?}", thread.id()));
+        let line = if self.json {
+            json!({
+                "time": time,
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "thread": thread,
+                "task_id": task_log.task_id,
+                "url": url,
+                "message": record.args().to_string(),
+            })
+            .to_string()
+        } else {
+            format!("{} {:<5} [{}] {}: {}", time, record.level(), thread, record.target(), record.args())
+        };
+        if let Err(e) = task_log.write_line(&line) {
+            match &self.screen {
+                Some(screen) => {
+                    let _ = screen.send(format!("ERROR: Failed to write log file: {}", e));
+                }
+                None => eprintln!("ERROR: Failed to write log file: {}", e),
+            }
+        }
+    }
+
+    fn flush(&self) {
+        for task_log in self.files.lock().unwrap().values_mut() {
+            let _ = task_log.file.flush();
+        }
+    }
+}
+
+impl TaskLog {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > MAX_LOG_FILE_SIZE {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    // spider.log -> spider.log.1 -> spider.log.2 ……，超出保留数量的最旧文件被覆盖
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |index: usize| PathBuf::from(format!("{}.{}", self.path.display(), index));
+        for index in (1..MAX_LOG_FILES).rev() {
+            let from = rotated(index);
+            if from.exists() {
+                fs::rename(&from, rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
 }
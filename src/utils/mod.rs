@@ -1,5 +1,11 @@
+pub mod atomic_file;  // 导入 atomic_file 模块
 pub mod cli;  // 导入 cli 模块
+pub mod config;  // 导入 config 模块
+pub mod disk;  // 导入 disk 模块
+pub mod format;  // 导入 format 模块
 pub mod logging;  // 导入 logging 模块
+pub mod output;  // 导入 output 模块
 
-pub use self::cli::parse_args;  // 导出 parse_args 函数
-// pub use self::logging::{info, warn, error};  // 导出 log 函数
+pub use self::atomic_file::atomic_write;  // 导出 atomic_write 函数
+pub use self::cli::{budget_overrides, graph_formats, list_options, parse_args, task_ref};  // 导出 parse_args 函数
+pub use self::config::TaskConfig;  // 导出任务配置
@@ -0,0 +1,36 @@
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// 终端输出方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+    /// 进度条和日志面板，用于交互式终端
+    Interactive,
+    /// 定期输出一行状态，警告和错误日志直接写到 stderr，用于 cron、systemd 或管道
+    Plain,
+    /// 只输出致命错误，日志只写入任务日志文件
+    Quiet,
+    /// 全屏仪表盘，显示吞吐量、进行中的请求、主机队列和最近的错误，可以用按键控制任务
+    Dashboard,
+}
+
+static OUTPUT_MODE: OnceLock<OutputMode> = OnceLock::new();
+
+/// 根据命令行参数和 stdout / stderr 是否为终端确定输出方式，不是终端时仪表盘退回为状态行
+pub fn init(quiet: bool, no_progress: bool, dashboard: bool) -> OutputMode {
+    let mode = if quiet {
+        OutputMode::Quiet
+    } else if no_progress || !std::io::stdout().is_terminal() || !std::io::stderr().is_terminal() {
+        OutputMode::Plain
+    } else if dashboard {
+        OutputMode::Dashboard
+    } else {
+        OutputMode::Interactive
+    };
+    *OUTPUT_MODE.get_or_init(|| mode)
+}
+
+/// 当前的输出方式，未初始化时为交互式
+pub fn output_mode() -> OutputMode {
+    OUTPUT_MODE.get().copied().unwrap_or(OutputMode::Interactive)
+}
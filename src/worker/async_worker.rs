@@ -0,0 +1,325 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use crossbeam::channel::Sender;
+use serde::{Serialize, Deserialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use reqwest::Url;
+use crate::fetcher::client::{Fetched, HttpClient, RUNTIME};
+use crate::parser::{content_hash, normalize_text, Link, Parser};
+use crate::store::{FetchRecord, TaskStore};
+use crate::utils::logging;
+use super::{control, Budget, CrawlMonitor, Duplicate, DuplicateDetector, LinkChecker, RunControl, TaskInfo, UrlOutcome};
+use super::dedup::DUPLICATE_REASON;
+// 日志宏
+use log::{debug, info, error};
+
+/// 抓取引擎配置，对应任务配置文件中的 `[engine]` 段
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EngineConfig {
+    #[serde(default)]
+    pub mode: EngineMode,
+    /// 异步引擎同时进行中的最大请求数
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// 停止任务时等待进行中请求完成的最长秒数，超时后取消这些请求，恢复任务时重新抓取
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineMode {
+    /// 每个 worker 一个线程，使用阻塞请求
+    #[default]
+    Thread,
+    /// 基于 tokio 的异步请求，解析在 CPU 线程池中进行
+    Async,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            mode: EngineMode::default(),
+            concurrency: default_concurrency(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+        }
+    }
+}
+
+fn default_concurrency() -> usize {
+    256
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+// 一次抓取加解析的结果
+struct CrawlResult {
+    url: String,
+    depth: u32,
+    links: Vec<String>,
+    record: Option<FetchRecord>,
+    // 正文的指纹，以及与哪个已抓取页面近似重复
+    fingerprint: Option<(u64, Option<Duplicate>)>,
+    skipped: Option<String>,
+    error: Option<String>,
+}
+
+/// 异步抓取引擎：请求数量由信号量限制，页面解析放到阻塞线程池中执行
+pub struct AsyncWorker {
+    http_client: Arc<HttpClient>,
+    seeds: Vec<(String, u32)>,
+    visited: Arc<Mutex<HashSet<String>>>,
+    task_info: Arc<Mutex<TaskInfo>>,
+    sender: Sender<TaskInfo>,
+    store: Arc<TaskStore>,
+    budget: Arc<Budget>,
+    monitor: Arc<CrawlMonitor>,
+    control: Arc<RunControl>,
+    dedup: Arc<DuplicateDetector>,
+    link_checker: Option<Arc<LinkChecker>>,
+    task_id: u32,
+    concurrency: usize,
+}
+
+impl AsyncWorker {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        http_client: Arc<HttpClient>,
+        seeds: Vec<(String, u32)>,
+        visited: Arc<Mutex<HashSet<String>>>,
+        task_info: Arc<Mutex<TaskInfo>>,
+        sender: Sender<TaskInfo>,
+        store: Arc<TaskStore>,
+        budget: Arc<Budget>,
+        monitor: Arc<CrawlMonitor>,
+        control: Arc<RunControl>,
+        dedup: Arc<DuplicateDetector>,
+        link_checker: Option<Arc<LinkChecker>>,
+        concurrency: usize,
+    ) -> Self {
+        let task_id = task_info.lock().unwrap().id;
+        Self {
+            http_client,
+            seeds,
+            visited,
+            task_info,
+            sender,
+            store,
+            budget,
+            monitor,
+            control,
+            dedup,
+            link_checker,
+            task_id,
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// 阻塞运行直到所有可达页面抓取完成
+    pub fn run(&self) {
+        logging::set_current_task(Some(self.task_id));
+        RUNTIME.block_on(self.crawl_all());
+        info!("Async engine finished!");
+    }
+
+    async fn crawl_all(&self) {
+        let fetch_permits = Arc::new(Semaphore::new(self.concurrency));
+        let parse_permits = Arc::new(Semaphore::new(num_cpus::get()));
+        let parser = Parser::new();
+        let deep = self.task_info.lock().unwrap().deep;
+
+        let mut frontier: VecDeque<(String, u32)> = self.seeds.iter().cloned().collect();
+        let mut in_flight = JoinSet::new();
+        let mut concurrency = self.concurrency;
+
+        loop {
+            // 按仪表盘中调整的并发数增减许可，正在使用的许可在请求完成后再收回
+            let target = self.control.concurrency().unwrap_or(concurrency);
+            if target > concurrency {
+                fetch_permits.add_permits(target - concurrency);
+                concurrency = target;
+            } else if target < concurrency {
+                concurrency -= fetch_permits.forget_permits(concurrency - target);
+            }
+
+            // 暂停期间不再发起新的请求，进行中的请求完成后等待恢复
+            if self.control.is_paused() && in_flight.is_empty() {
+                tokio::time::sleep(control::PAUSE_POLL).await;
+                continue;
+            }
+            while !self.control.is_paused() {
+                let Some((url, depth)) = frontier.pop_front() else {
+                    break;
+                };
+                if self.control.is_stopping() || self.budget.is_stopped() {
+                    frontier.clear();
+                    break;
+                }
+                {
+                    let mut visited = self.visited.lock().unwrap();
+                    // 超出预算的 URL 留在待抓取队列中，不标记为已访问
+                    if depth > deep || visited.contains(&url) || !self.budget.admit(&url) {
+                        continue;
+                    }
+                    visited.insert(url.clone());
+                }
+                if let Err(e) = self.store.mark_visited(self.task_id, &url) {
+                    error!(url = url.as_str(); "Failed to mark {} as visited: {}", url, e);
+                }
+                self.task_info.lock().unwrap().start_url();
+
+                // 达到并发上限时在此等待，直到有请求完成释放许可
+                let Some(fetch_permit) = self.control.cancellable(Arc::clone(&fetch_permits).acquire_owned()).await else {
+                    break;
+                };
+                let fetch_permit = fetch_permit.unwrap();
+                self.monitor.start("async", &url);
+                let http_client = Arc::clone(&self.http_client);
+                let parse_permits = Arc::clone(&parse_permits);
+                let parser = parser.clone();
+                let dedup = Arc::clone(&self.dedup);
+                let link_checker = self.link_checker.clone();
+
+                // 请求中的日志写入所属任务的日志文件
+                in_flight.spawn(logging::in_task(self.task_id, async move {
+                    info!(url = url.as_str(); "Async fetching: {}", url);
+                    let started = Instant::now();
+                    let fetched = http_client.get_async(&url).await;
+                    let elapsed_ms = started.elapsed().as_millis() as u64;
+                    drop(fetch_permit);
+
+                    let mut result = CrawlResult { url, depth, links: Vec::new(), record: None, fingerprint: None, skipped: None, error: None };
+                    match fetched {
+                        Ok(Fetched::Page(page)) => {
+                            let _parse_permit = parse_permits.acquire_owned().await.unwrap();
+                            let page_url = result.url.clone();
+                            let body = page.body;
+                            let content_length = body.len();
+                            let success = (200..300).contains(&page.status);
+                            let detector = Arc::clone(&dedup);
+                            let parsed = tokio::task::spawn_blocking(move || {
+                                let base = Url::parse(&page_url).map_err(|e| e.to_string())?;
+                                let (links, is_content_page, title, text) = parser.parse_content(&body, &base).map_err(|e| e.to_string())?;
+                                // 在解析线程中把相对链接转换为绝对地址，并计算摘要和规范化正文
+                                let links: Vec<Link> = links.into_iter()
+                                    .filter_map(|link| Some(Link { href: base.join(&link.href).ok()?.to_string(), ..link }))
+                                    .collect();
+                                let text = text.filter(|_| is_content_page).map(|text| normalize_text(&text)).unwrap_or_default();
+                                let fingerprint = detector.fingerprint(&text).filter(|_| success);
+                                Ok::<_, String>((links, title.filter(|_| is_content_page), content_hash(&body), text, fingerprint))
+                            })
+                            .await
+                            .unwrap_or_else(|e| Err(format!("Parser task failed: {}", e)));
+                            match parsed {
+                                Ok((links, title, content_hash, text, fingerprint)) => {
+                                    let duplicate = fingerprint.and_then(|fingerprint| dedup.check(&result.url, fingerprint));
+                                    result.fingerprint = fingerprint.map(|fingerprint| (fingerprint, duplicate.clone()));
+                                    // 跳过的近似重复页面不保存抓取结果，也不跟踪其中的链接
+                                    if duplicate.is_some() && dedup.skips_duplicates() {
+                                        result.skipped = Some(DUPLICATE_REASON.to_string());
+                                        return result;
+                                    }
+                                    result.links = links.iter().map(|link| link.href.clone()).collect();
+                                    // 死链检查模式下只跟踪范围内的链接，范围外的链接只检查不抓取
+                                    if let Some(checker) = &link_checker {
+                                        checker.check_async(&result.links).await;
+                                        result.links.retain(|link| checker.in_scope(link));
+                                    }
+                                    result.record = Some(FetchRecord {
+                                        url: result.url.clone(),
+                                        final_url: page.final_url,
+                                        depth,
+                                        status: page.status,
+                                        content_type: page.content_type,
+                                        content_length,
+                                        title,
+                                        elapsed_ms,
+                                        content_hash,
+                                        text,
+                                        links,
+                                    });
+                                }
+                                Err(e) => result.error = Some(format!("Failed to parse content: {}", e)),
+                            }
+                        }
+                        Ok(Fetched::Skipped(reason)) => result.skipped = Some(reason),
+                        Err(e) => result.error = Some(e.to_string()),
+                    }
+
+                    result
+                }));
+            }
+
+            // 停止等待超时后取消进行中的请求，未完成的 URL 留在待抓取队列中
+            let Some(joined) = self.control.cancellable(in_flight.join_next()).await else {
+                info!("Cancelled {} in-flight requests", in_flight.len());
+                in_flight.abort_all();
+                break;
+            };
+            match joined {
+                Some(Ok(result)) => {
+                    if let Some(message) = &result.error {
+                        error!(url = result.url.as_str(); "Error fetching URL {}: {}", result.url, message);
+                        if let Err(e) = self.store.record_error(self.task_id, &result.url, message) {
+                            error!(url = result.url.as_str(); "Failed to save error for {}: {}", result.url, e);
+                        }
+                    }
+                    if let Some((fingerprint, duplicate)) = &result.fingerprint {
+                        if let Some(duplicate) = duplicate {
+                            info!(url = result.url.as_str(); "{} is a near duplicate of {} ({:.0}% similar)", result.url, duplicate.of, duplicate.similarity * 100.0);
+                        }
+                        if let Err(e) = self.store.record_fingerprint(self.task_id, &result.url, *fingerprint, duplicate.as_ref()) {
+                            error!(url = result.url.as_str(); "Failed to save fingerprint of {}: {}", result.url, e);
+                        }
+                    }
+                    debug!(url = result.url.as_str(); "Links-Count: {} ({})", result.links.len(), result.url);
+                    let next_links: Vec<(String, u32)> = if result.depth < deep {
+                        let visited = self.visited.lock().unwrap();
+                        result.links.into_iter()
+                            .filter(|link| !visited.contains(link))
+                            .map(|link| (link, result.depth + 1))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    // 保存抓取结果，并把子链接写入待抓取队列
+                    let (outcome, discovered) = if let Some(record) = &result.record {
+                        let discovered = self.store.record_fetch(self.task_id, record, &next_links).unwrap_or_else(|e| {
+                            error!(url = result.url.as_str(); "Failed to save fetch result for {}: {}", result.url, e);
+                            0
+                        });
+                        (UrlOutcome::Fetched, discovered)
+                    } else if let Some(reason) = &result.skipped {
+                        if let Err(e) = self.store.record_skip(self.task_id, &result.url, reason) {
+                            error!(url = result.url.as_str(); "Failed to save skipped URL {}: {}", result.url, e);
+                        }
+                        (UrlOutcome::Skipped, 0)
+                    } else {
+                        (UrlOutcome::Failed, 0)
+                    };
+                    match &result.record {
+                        Some(record) => self.budget.record_bytes(record.content_length as u64),
+                        None => self.budget.release(&result.url),
+                    }
+                    match (&result.record, &result.skipped) {
+                        (Some(record), _) => self.monitor.fetched(record),
+                        (None, Some(reason)) => self.monitor.skipped(&result.url, result.depth, reason),
+                        (None, None) => self.monitor.failed(&result.url, result.depth),
+                    }
+                    frontier.extend(next_links);
+
+                    let mut task_info = self.task_info.lock().unwrap();
+                    task_info.finish_url(outcome, discovered);
+                    // 通知主线程任务进度变化，主线程停止等待后忽略发送失败
+                    let _ = self.sender.send(task_info.clone());
+                }
+                Some(Err(e)) => error!("Crawl task failed: {}", e),
+                None => break,
+            }
+        }
+    }
+}
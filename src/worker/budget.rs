@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use reqwest::Url;
+use serde::{Serialize, Deserialize};
+
+/// 抓取预算，对应任务配置文件中的 `[budget]` 段，未设置的项不限制
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// 任务累计抓取的最大页面数
+    pub max_pages: Option<u32>,
+    /// 任务累计下载的最大字节数
+    pub max_bytes: Option<u64>,
+    /// 每次运行的最长时间（秒）
+    pub max_duration_secs: Option<u64>,
+    /// 每个主机最多抓取的页面数
+    pub max_pages_per_host: Option<u32>,
+    /// 按路径前缀限制的页面数，例如 `"/blog/" = 100`
+    pub max_pages_per_prefix: BTreeMap<String, u32>,
+}
+
+impl BudgetConfig {
+    /// 用 `other` 中设置的项覆盖当前预算
+    pub fn merge(&mut self, other: &BudgetConfig) {
+        self.max_pages = other.max_pages.or(self.max_pages);
+        self.max_bytes = other.max_bytes.or(self.max_bytes);
+        self.max_duration_secs = other.max_duration_secs.or(self.max_duration_secs);
+        self.max_pages_per_host = other.max_pages_per_host.or(self.max_pages_per_host);
+        self.max_pages_per_prefix.extend(other.max_pages_per_prefix.iter().map(|(prefix, max)| (prefix.clone(), *max)));
+    }
+}
+
+#[derive(Default)]
+struct Usage {
+    pages: u32,
+    bytes: u64,
+    hosts: HashMap<String, u32>,
+    prefixes: HashMap<String, u32>,
+    // 用尽后停止整个任务的预算项（页面数、字节数、时长）
+    stopped: Option<&'static str>,
+    // 只推迟部分 URL 的预算项（每个主机、每个路径前缀）
+    deferred: Option<&'static str>,
+}
+
+/// 运行中的预算计数，worker 在发起请求前检查；超出预算的 URL 留在待抓取队列中，提高预算后可以继续抓取
+pub struct Budget {
+    config: BudgetConfig,
+    started: Instant,
+    usage: Mutex<Usage>,
+}
+
+impl Budget {
+    /// `fetched` 为任务已抓取页面的 URL 和大小，计入累计的页面数和字节数
+    pub fn new(config: BudgetConfig, fetched: &[(String, u64)]) -> Self {
+        let budget = Budget { config, started: Instant::now(), usage: Mutex::new(Usage::default()) };
+        {
+            let mut usage = budget.usage.lock().unwrap();
+            for (url, bytes) in fetched {
+                budget.count(&mut usage, url, 1);
+                usage.bytes += bytes;
+            }
+        }
+        budget
+    }
+
+    /// 检查 URL 是否在预算内，在预算内时预占一个页面；返回 false 时不应抓取该 URL
+    pub fn admit(&self, url: &str) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        if let Some(limit) = self.check_total(&usage) {
+            usage.stopped.get_or_insert(limit);
+            return false;
+        }
+        if let Some(limit) = self.check_url(&usage, url) {
+            usage.deferred.get_or_insert(limit);
+            return false;
+        }
+        self.count(&mut usage, url, 1);
+        true
+    }
+
+    /// 已预占的 URL 没有抓取到页面（失败或跳过）时归还预算
+    pub fn release(&self, url: &str) {
+        let mut usage = self.usage.lock().unwrap();
+        self.count(&mut usage, url, -1);
+    }
+
+    /// 记录下载的字节数
+    pub fn record_bytes(&self, bytes: u64) {
+        self.usage.lock().unwrap().bytes += bytes;
+    }
+
+    /// 停止整个任务的预算项是否已用尽，用尽后 worker 不再调度新的 URL
+    pub fn is_stopped(&self) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        if usage.stopped.is_none() {
+            usage.stopped = self.check_total(&usage);
+        }
+        usage.stopped.is_some()
+    }
+
+    /// 被用尽的预算项，优先返回停止整个任务的预算项；本次运行中没有 URL 因预算被推迟时为 None
+    pub fn exhausted(&self) -> Option<&'static str> {
+        let usage = self.usage.lock().unwrap();
+        usage.stopped.or(usage.deferred)
+    }
+
+    // 返回已用尽的任务总预算项
+    fn check_total(&self, usage: &Usage) -> Option<&'static str> {
+        let config = &self.config;
+        if config.max_pages.is_some_and(|max| usage.pages >= max) {
+            return Some("max pages");
+        }
+        if config.max_bytes.is_some_and(|max| usage.bytes >= max) {
+            return Some("max bytes");
+        }
+        if config.max_duration_secs.is_some_and(|max| self.started.elapsed() >= Duration::from_secs(max)) {
+            return Some("max duration");
+        }
+        None
+    }
+
+    // 返回 URL 所在主机或路径前缀已用尽的预算项
+    fn check_url(&self, usage: &Usage, url: &str) -> Option<&'static str> {
+        let config = &self.config;
+        let Ok(url) = Url::parse(url) else {
+            return None;
+        };
+        if let (Some(max), Some(host)) = (config.max_pages_per_host, url.host_str()) {
+            if usage.hosts.get(host).copied().unwrap_or(0) >= max {
+                return Some("max pages per host");
+            }
+        }
+        for (prefix, max) in &config.max_pages_per_prefix {
+            if url.path().starts_with(prefix.as_str()) && usage.prefixes.get(prefix).copied().unwrap_or(0) >= *max {
+                return Some("max pages per path prefix");
+            }
+        }
+        None
+    }
+
+    fn count(&self, usage: &mut Usage, url: &str, delta: i32) {
+        usage.pages = usage.pages.saturating_add_signed(delta);
+        let Ok(url) = Url::parse(url) else {
+            return;
+        };
+        if self.config.max_pages_per_host.is_some() {
+            if let Some(host) = url.host_str() {
+                let count = usage.hosts.entry(host.to_string()).or_insert(0);
+                *count = count.saturating_add_signed(delta);
+            }
+        }
+        for prefix in self.config.max_pages_per_prefix.keys() {
+            if url.path().starts_with(prefix.as_str()) {
+                let count = usage.prefixes.entry(prefix.clone()).or_insert(0);
+                *count = count.saturating_add_signed(delta);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_pages_stops_the_task() {
+        let budget = Budget::new(BudgetConfig { max_pages: Some(2), ..BudgetConfig::default() }, &[]);
+        assert!(budget.admit("http://a.com/1"));
+        assert!(!budget.is_stopped());
+        assert!(budget.admit("http://a.com/2"));
+        assert!(budget.is_stopped());
+        assert!(!budget.admit("http://a.com/3"));
+        assert_eq!(budget.exhausted(), Some("max pages"));
+    }
+
+    #[test]
+    fn released_pages_are_returned() {
+        let budget = Budget::new(BudgetConfig { max_pages: Some(1), ..BudgetConfig::default() }, &[]);
+        assert!(budget.admit("http://a.com/1"));
+        budget.release("http://a.com/1");
+        assert!(budget.admit("http://a.com/2"));
+        assert_eq!(budget.exhausted(), None);
+    }
+
+    #[test]
+    fn previous_runs_count_towards_the_budget() {
+        let fetched = [("http://a.com/1".to_string(), 600), ("http://a.com/2".to_string(), 600)];
+        let budget = Budget::new(BudgetConfig { max_bytes: Some(1000), ..BudgetConfig::default() }, &fetched);
+        assert!(budget.is_stopped());
+        assert_eq!(budget.exhausted(), Some("max bytes"));
+    }
+
+    #[test]
+    fn per_host_limit_defers_without_stopping() {
+        let budget = Budget::new(BudgetConfig { max_pages_per_host: Some(1), ..BudgetConfig::default() }, &[]);
+        assert!(budget.admit("http://a.com/1"));
+        assert!(!budget.admit("http://a.com/2"));
+        assert!(budget.admit("http://b.com/1"));
+        assert!(!budget.is_stopped());
+        assert_eq!(budget.exhausted(), Some("max pages per host"));
+    }
+
+    #[test]
+    fn per_prefix_limit_only_applies_under_the_prefix() {
+        let config = BudgetConfig { max_pages_per_prefix: BTreeMap::from([("/blog/".to_string(), 1)]), ..BudgetConfig::default() };
+        let budget = Budget::new(config, &[]);
+        assert!(budget.admit("http://a.com/blog/1"));
+        assert!(!budget.admit("http://a.com/blog/2"));
+        assert!(budget.admit("http://a.com/docs/1"));
+    }
+}
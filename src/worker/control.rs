@@ -0,0 +1,152 @@
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::Notify;
+use crate::TERMINATE;
+
+/// 暂停控制文件名，任务目录中存在该文件时运行中的任务暂停
+pub const PAUSE_FILE: &str = "pause";
+
+/// worker 检查暂停状态的间隔
+pub const PAUSE_POLL: Duration = Duration::from_millis(200);
+
+// 信号处理函数只记录最近一次请求并增加序号，由运行任务的线程转换为控制文件；
+// 每个任务记下已处理的序号，同一进程中运行的所有任务都会处理同一个信号
+const NO_REQUEST: u8 = 0;
+const PAUSE_REQUEST: u8 = 1;
+const RESUME_REQUEST: u8 = 2;
+static SIGNAL_REQUEST: AtomicU8 = AtomicU8::new(NO_REQUEST);
+static SIGNAL_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 注册 SIGUSR1（暂停）和 SIGUSR2（继续）的处理函数，信号作用于进程中运行的所有任务；
+/// 运行任务和 `spider serve` 的进程都需要注册，否则信号的默认行为会结束进程
+#[cfg(unix)]
+pub fn install_signal_handlers() {
+    extern "C" fn on_signal(signal: libc::c_int) {
+        let request = if signal == libc::SIGUSR1 { PAUSE_REQUEST } else { RESUME_REQUEST };
+        SIGNAL_REQUEST.store(request, Ordering::SeqCst);
+        SIGNAL_SEQ.fetch_add(1, Ordering::SeqCst);
+    }
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGUSR1, handler);
+        libc::signal(libc::SIGUSR2, handler);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_signal_handlers() {}
+
+/// 创建或删除任务目录中的控制文件
+pub fn set_paused(task_dir: &str, paused: bool) -> io::Result<()> {
+    let path = Path::new(task_dir).join(PAUSE_FILE);
+    if paused {
+        fs::write(path, b"")
+    } else {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 一次运行的控制状态：停止、暂停和调整后的并发数，由运行任务的线程设置，worker 在发起下一个请求前检查
+pub struct RunControl {
+    stop: AtomicBool,
+    paused: AtomicBool,
+    // 仪表盘中调整的异步引擎并发数，0 表示使用任务配置
+    concurrency: AtomicUsize,
+    // 已处理的信号序号，创建之前收到的信号不作用于本任务
+    signal_seen: AtomicU64,
+    // 停止等待超时后取消进行中的请求
+    cancelled: AtomicBool,
+    cancel: Notify,
+}
+
+impl Default for RunControl {
+    fn default() -> Self {
+        RunControl {
+            stop: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            concurrency: AtomicUsize::new(0),
+            signal_seen: AtomicU64::new(SIGNAL_SEQ.load(Ordering::SeqCst)),
+            cancelled: AtomicBool::new(false),
+            cancel: Notify::new(),
+        }
+    }
+}
+
+impl RunControl {
+    /// 停止本次运行：不再调度新的 URL，等待进行中的请求完成
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// 停止本次运行并取消进行中的请求，被取消的 URL 留在待抓取队列中，恢复任务时重新抓取
+    pub fn cancel(&self) {
+        self.stop();
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancel.notify_waiters();
+    }
+
+    /// 运行 future，本次运行被取消时立即结束并返回 None
+    pub async fn cancellable<F: Future>(&self, future: F) -> Option<F::Output> {
+        let cancelled = self.cancel.notified();
+        tokio::pin!(cancelled);
+        // 先登记等待再检查标志，避免错过检查之后、等待之前发出的通知
+        cancelled.as_mut().enable();
+        if self.cancelled.load(Ordering::SeqCst) {
+            return None;
+        }
+        tokio::select! {
+            output = future => Some(output),
+            _ = cancelled => None,
+        }
+    }
+
+    /// 本次运行被停止或进程收到了终止信号
+    pub fn is_stopping(&self) -> bool {
+        self.stop.load(Ordering::SeqCst) || TERMINATE.load(Ordering::SeqCst)
+    }
+
+    /// 把收到的信号转换为控制文件，并按控制文件更新暂停状态，返回任务是否暂停
+    pub fn poll(&self, task_dir: &str) -> io::Result<bool> {
+        let seq = SIGNAL_SEQ.load(Ordering::SeqCst);
+        if self.signal_seen.swap(seq, Ordering::SeqCst) != seq {
+            match SIGNAL_REQUEST.load(Ordering::SeqCst) {
+                PAUSE_REQUEST => set_paused(task_dir, true)?,
+                RESUME_REQUEST => set_paused(task_dir, false)?,
+                _ => {}
+            }
+        }
+        let paused = Path::new(task_dir).join(PAUSE_FILE).exists();
+        self.paused.store(paused, Ordering::SeqCst);
+        Ok(paused)
+    }
+
+    /// 任务是否处于暂停状态，停止后不再暂停
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst) && !self.is_stopping()
+    }
+
+    /// 暂停期间阻塞当前线程
+    pub fn wait_while_paused(&self) {
+        while self.is_paused() {
+            thread::sleep(PAUSE_POLL);
+        }
+    }
+
+    /// 调整异步引擎的最大并发请求数，None 表示恢复为任务配置
+    pub fn set_concurrency(&self, concurrency: Option<usize>) {
+        self.concurrency.store(concurrency.map_or(0, |n| n.max(1)), Ordering::SeqCst);
+    }
+
+    /// 调整后的并发数，没有调整时为 None
+    pub fn concurrency(&self) -> Option<usize> {
+        Some(self.concurrency.load(Ordering::SeqCst)).filter(|n| *n > 0)
+    }
+}
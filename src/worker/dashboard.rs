@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, Paragraph, Row, Sparkline, Table};
+use reqwest::Url;
+use crate::store::TaskStore;
+use crate::utils::logging;
+use crate::TERMINATE;
+use super::{control, CrawlMonitor, EngineMode, MonitorSnapshot, RunControl, TaskInfo, TaskStatus};
+// 日志宏
+use log::{info, error};
+
+// 两次绘制之间的最短间隔
+const DRAW_INTERVAL: Duration = Duration::from_millis(200);
+// 吞吐量的采样间隔和保留的采样数
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_SAMPLES: usize = 300;
+// 从任务存储重新统计各主机队列的间隔
+const HOSTS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 运行中任务的全屏仪表盘，由主线程在等待 worker 期间定期调用 `update` 刷新并处理按键
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+    view: View,
+}
+
+// 仪表盘的显示状态
+struct View {
+    task_dir: String,
+    control: Arc<RunControl>,
+    engine: EngineMode,
+    // 异步引擎为最大并发请求数，线程引擎为线程数
+    concurrency: usize,
+    started: Instant,
+    // 每秒完成的 URL 数
+    throughput: VecDeque<u64>,
+    last_sample: (Instant, u32),
+    queued_hosts: Vec<(String, u32)>,
+    last_hosts: Option<Instant>,
+    last_draw: Option<Instant>,
+    message: String,
+    // 退出按键的次数，原始模式下 Ctrl+C 是按键而不是 SIGINT，第二次按下时强制退出
+    quit_presses: u32,
+}
+
+impl Dashboard {
+    /// 切换到终端的备用屏幕；`progress` 为本次运行开始时已完成的 URL 数
+    pub fn open(task_dir: &str, control: Arc<RunControl>, engine: EngineMode, concurrency: usize, progress: u32) -> io::Result<Self> {
+        let terminal = ratatui::try_init()?;
+        let view = View {
+            task_dir: task_dir.to_string(),
+            control,
+            engine,
+            concurrency,
+            started: Instant::now(),
+            throughput: VecDeque::with_capacity(MAX_SAMPLES),
+            last_sample: (Instant::now(), progress),
+            queued_hosts: Vec::new(),
+            last_hosts: None,
+            last_draw: None,
+            message: String::new(),
+            quit_presses: 0,
+        };
+        Ok(Dashboard { terminal, view })
+    }
+
+    /// 处理按键、采样吞吐量并重新绘制
+    pub fn update(&mut self, task: &TaskInfo, monitor: &CrawlMonitor, store: &TaskStore) {
+        let view = &mut self.view;
+        if let Err(e) = view.handle_keys() {
+            error!("Failed to read dashboard input: {}", e);
+        }
+
+        if view.last_sample.0.elapsed() >= SAMPLE_INTERVAL {
+            if view.throughput.len() == MAX_SAMPLES {
+                view.throughput.pop_front();
+            }
+            view.throughput.push_back(task.progress.saturating_sub(view.last_sample.1) as u64);
+            view.last_sample = (Instant::now(), task.progress);
+        }
+        if view.last_hosts.is_none_or(|last| last.elapsed() >= HOSTS_INTERVAL) {
+            match store.queued_hosts(task.id) {
+                Ok(hosts) => view.queued_hosts = hosts,
+                Err(e) => error!("Failed to count queued URLs by host: {}", e),
+            }
+            view.last_hosts = Some(Instant::now());
+        }
+
+        if view.last_draw.is_some_and(|last| last.elapsed() < DRAW_INTERVAL) {
+            return;
+        }
+        view.last_draw = Some(Instant::now());
+        if let Err(e) = self.terminal.draw(|frame| view.draw(frame, task, monitor)) {
+            error!("Failed to draw dashboard: {}", e);
+        }
+    }
+}
+
+impl View {
+    // p 暂停，r 继续，+ / - 调整并发数，q、Esc 或 Ctrl+C 停止任务，再按一次强制退出
+    fn handle_keys(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('p') => {
+                    control::set_paused(&self.task_dir, true)?;
+                    self.message = "Pausing, in-flight requests will finish first".to_string();
+                }
+                KeyCode::Char('r') => {
+                    control::set_paused(&self.task_dir, false)?;
+                    self.message = "Resuming".to_string();
+                }
+                KeyCode::Char(c @ ('+' | '=' | '-')) => self.change_concurrency(c != '-'),
+                KeyCode::Char('q') | KeyCode::Esc => self.quit(),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit(),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn change_concurrency(&mut self, increase: bool) {
+        if self.engine != EngineMode::Async {
+            self.message = "Concurrency can only be changed with the async engine".to_string();
+            return;
+        }
+        let step = (self.concurrency / 10).max(1);
+        self.concurrency = if increase { self.concurrency + step } else { self.concurrency.saturating_sub(step).max(1) };
+        self.control.set_concurrency(Some(self.concurrency));
+        info!("Concurrency changed to {}", self.concurrency);
+        self.message = format!("Concurrency set to {}", self.concurrency);
+    }
+
+    // 与 Ctrl+C 相同：第一次不再调度新的 URL，等待进行中的请求完成后保存状态；第二次恢复终端后立即退出
+    fn quit(&mut self) {
+        self.quit_presses += 1;
+        if self.quit_presses > 1 {
+            ratatui::restore();
+            crate::force_exit();
+        }
+        TERMINATE.store(true, Ordering::SeqCst);
+        self.message = "Stopping, waiting for in-flight requests (press q again to force quit)".to_string();
+    }
+
+    fn draw(&self, frame: &mut Frame, task: &TaskInfo, monitor: &CrawlMonitor) {
+        let snapshot = monitor.snapshot();
+        let [header, gauge, sparkline, middle, bottom, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(6),
+            Constraint::Min(6),
+            Constraint::Length(10),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        // 任务状态和计数
+        let elapsed = self.started.elapsed().as_secs();
+        let workers = match self.engine {
+            EngineMode::Async => format!("async, concurrency {}", self.concurrency),
+            EngineMode::Thread => format!("{} threads", self.concurrency),
+        };
+        let status_style = match task.status {
+            TaskStatus::Paused => Style::new().fg(Color::Yellow),
+            _ if self.control.is_stopping() => Style::new().fg(Color::Red),
+            _ => Style::new().fg(Color::Green),
+        };
+        let status = if self.control.is_stopping() { "stopping".to_string() } else { task.status.to_string() };
+        let lines = vec![
+            Line::from(vec![
+                format!("Task {} ({}) ", task.id, task.name).bold(),
+                Span::styled(status, status_style),
+                format!("  [{:02}:{:02}:{:02}]  {}", elapsed / 3600, elapsed / 60 % 60, elapsed % 60, workers).into(),
+            ]),
+            Line::from(format!(
+                "{} fetched, {} failed, {} skipped, {} queued, {} in flight",
+                task.fetched, task.failed, task.skipped, task.queued, task.in_flight
+            )),
+        ];
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" spider ")), header);
+
+        let ratio = if task.discovered == 0 { 0.0 } else { task.progress as f64 / task.discovered as f64 };
+        frame.render_widget(
+            Gauge::default()
+                .gauge_style(Style::new().fg(Color::Cyan))
+                .ratio(ratio.min(1.0))
+                .label(format!("{}/{} URLs done", task.progress, task.discovered)),
+            gauge,
+        );
+
+        // 吞吐量，只显示放得下的最近采样
+        let width = sparkline.width.saturating_sub(2) as usize;
+        let samples: Vec<u64> = self.throughput.iter().skip(self.throughput.len().saturating_sub(width)).copied().collect();
+        let current = samples.last().copied().unwrap_or(0);
+        let peak = samples.iter().max().copied().unwrap_or(0);
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(format!(" Throughput: {} URLs/s (peak {}) ", current, peak)))
+                .style(Style::new().fg(Color::Green))
+                .data(&samples),
+            sparkline,
+        );
+
+        let [in_flight, hosts] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(middle);
+        self.draw_in_flight(frame, in_flight, &snapshot.in_flight);
+        self.draw_hosts(frame, hosts, &snapshot);
+
+        let [codes, errors] = Layout::horizontal([Constraint::Length(24), Constraint::Min(20)]).areas(bottom);
+        let mut rows: Vec<Row> = snapshot.status_codes.iter()
+            .map(|(code, count)| Row::new(vec![code.to_string(), count.to_string()]).style(status_code_style(*code)))
+            .collect();
+        rows.push(Row::new(vec!["failed".to_string(), task.failed.to_string()]).style(Style::new().fg(Color::Red)));
+        rows.push(Row::new(vec!["skipped".to_string(), task.skipped.to_string()]));
+        frame.render_widget(
+            Table::new(rows, [Constraint::Length(8), Constraint::Min(6)]).block(Block::bordered().title(" Status codes ")),
+            codes,
+        );
+
+        let recent = logging::recent_errors();
+        let visible = errors.height.saturating_sub(2) as usize;
+        let items: Vec<ListItem> = recent.iter().skip(recent.len().saturating_sub(visible)).map(|line| ListItem::new(line.as_str())).collect();
+        frame.render_widget(
+            List::new(items).style(Style::new().fg(Color::Red)).block(Block::bordered().title(" Recent errors ")),
+            errors,
+        );
+
+        let keys = "p pause  r resume  +/- concurrency  q quit";
+        let footer_text = if self.message.is_empty() { keys.to_string() } else { format!("{}  |  {}", self.message, keys) };
+        frame.render_widget(Paragraph::new(footer_text).style(Style::new().fg(Color::DarkGray)), footer);
+    }
+
+    // 每个 worker 进行中的 URL，最久的在前
+    fn draw_in_flight(&self, frame: &mut Frame, area: Rect, in_flight: &[(String, String, Duration)]) {
+        let rows = in_flight.iter()
+            .map(|(worker, url, elapsed)| Row::new(vec![worker.clone(), format!("{:.1}s", elapsed.as_secs_f64()), url.clone()]));
+        let table = Table::new(rows, [Constraint::Length(10), Constraint::Length(7), Constraint::Min(10)])
+            .header(Row::new(vec!["Worker", "Time", "URL"]).bold())
+            .block(Block::bordered().title(format!(" In flight ({}) ", in_flight.len())));
+        frame.render_widget(table, area);
+    }
+
+    // 各主机待抓取的 URL 数、进行中的请求数和相邻请求的平均间隔
+    fn draw_hosts(&self, frame: &mut Frame, area: Rect, snapshot: &MonitorSnapshot) {
+        let mut hosts: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        for (host, queued) in &self.queued_hosts {
+            hosts.entry(host.clone()).or_default().0 = *queued;
+        }
+        for (_, url, _) in &snapshot.in_flight {
+            if let Some(host) = Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)) {
+                hosts.entry(host).or_default().1 += 1;
+            }
+        }
+        let mut hosts: Vec<_> = hosts.into_iter().collect();
+        hosts.sort_by(|a, b| (b.1.0 + b.1.1).cmp(&(a.1.0 + a.1.1)).then(a.0.cmp(&b.0)));
+
+        let rows = hosts.iter().map(|(host, (queued, in_flight))| {
+            let interval = snapshot.host_intervals.get(host)
+                .map(|interval| format!("{}ms", interval.as_millis()))
+                .unwrap_or_else(|| "-".to_string());
+            Row::new(vec![host.to_string(), queued.to_string(), in_flight.to_string(), interval])
+        });
+        let table = Table::new(rows, [Constraint::Min(12), Constraint::Length(8), Constraint::Length(6), Constraint::Length(9)])
+            .header(Row::new(vec!["Host", "Queued", "Active", "Interval"]).bold())
+            .block(Block::bordered().title(format!(" Hosts ({}) ", hosts.len())));
+        frame.render_widget(table, area);
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+fn status_code_style(code: u16) -> Style {
+    match code {
+        200..=299 => Style::new().fg(Color::Green),
+        300..=399 => Style::new().fg(Color::Cyan),
+        400..=499 => Style::new().fg(Color::Yellow),
+        _ => Style::new().fg(Color::Red),
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+use crate::parser::simhash::{similarity, simhash};
+
+/// 跳过近似重复页面时记录的原因
+pub const DUPLICATE_REASON: &str = "near duplicate";
+
+/// 近似重复检测，对应任务配置文件中的 `[dedup]` 段
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DedupConfig {
+    /// 是否检测近似重复的页面
+    pub enabled: bool,
+    /// 两个页面的 SimHash 指纹中相同位数的比例达到该值时视为近似重复（0 到 1）
+    pub threshold: f64,
+    /// 正文少于这么多词的页面不参与检测
+    pub min_words: usize,
+    /// 发现近似重复页面时的处理方式
+    pub action: DedupAction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupAction {
+    /// 照常保存抓取结果，并记录它与哪个页面重复
+    #[default]
+    Mark,
+    /// 不保存抓取结果，也不跟踪页面中的链接，记为跳过
+    Skip,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig { enabled: true, threshold: 0.9, min_words: 50, action: DedupAction::default() }
+    }
+}
+
+impl DedupConfig {
+    /// 检查相似度阈值是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(format!("dedup threshold must be between 0 and 1, got {}", self.threshold));
+        }
+        Ok(())
+    }
+}
+
+/// 与已抓取页面近似重复的页面
+#[derive(Clone, Debug)]
+pub struct Duplicate {
+    /// 最相似的已抓取页面
+    pub of: String,
+    pub similarity: f64,
+}
+
+/// 运行中的近似重复检测，worker 共用；只保存非重复页面的指纹，重复页面归到最先抓取的页面下
+pub struct DuplicateDetector {
+    config: DedupConfig,
+    pages: Mutex<Vec<(u64, String)>>,
+}
+
+impl DuplicateDetector {
+    /// `fingerprints` 为任务之前抓取的非重复页面的 URL 和指纹
+    pub fn new(config: DedupConfig, fingerprints: Vec<(String, u64)>) -> Self {
+        let pages = fingerprints.into_iter().map(|(url, fingerprint)| (fingerprint, url)).collect();
+        DuplicateDetector { config, pages: Mutex::new(pages) }
+    }
+
+    /// 计算正文的指纹，未开启检测或正文太短时返回 None
+    pub fn fingerprint(&self, text: &str) -> Option<u64> {
+        self.config.enabled.then(|| simhash(text, self.config.min_words)).flatten()
+    }
+
+    /// 与已抓取的页面比较，近似重复时返回最相似的页面，否则记下该页面的指纹
+    pub fn check(&self, url: &str, fingerprint: u64) -> Option<Duplicate> {
+        let mut pages = self.pages.lock().unwrap();
+        let duplicate = pages.iter()
+            .filter(|(_, page)| page != url)
+            .map(|(other, page)| (similarity(fingerprint, *other), page))
+            .filter(|(similarity, _)| *similarity >= self.config.threshold)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(similarity, page)| Duplicate { of: page.clone(), similarity });
+        if duplicate.is_none() {
+            pages.push((fingerprint, url.to_string()));
+        }
+        duplicate
+    }
+
+    /// 近似重复的页面是否跳过
+    pub fn skips_duplicates(&self) -> bool {
+        self.config.action == DedupAction::Skip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_pages_at_or_above_the_threshold() {
+        let detector = DuplicateDetector::new(DedupConfig { threshold: 0.75, ..DedupConfig::default() }, vec![("http://a.com/1".to_string(), 0)]);
+        // 64 位中 16 位不同，相似度正好为 0.75
+        let duplicate = detector.check("http://a.com/2", 0xffff).unwrap();
+        assert_eq!(duplicate.of, "http://a.com/1");
+        assert_eq!(duplicate.similarity, 0.75);
+        assert!(detector.check("http://a.com/3", 0x1ffff).is_none());
+    }
+
+    #[test]
+    fn keeps_only_fingerprints_of_original_pages() {
+        let detector = DuplicateDetector::new(DedupConfig::default(), Vec::new());
+        assert!(detector.check("http://a.com/1", 0).is_none());
+        assert_eq!(detector.check("http://a.com/2", 1).unwrap().of, "http://a.com/1");
+        assert_eq!(detector.check("http://a.com/3", 3).unwrap().of, "http://a.com/1");
+        // 重新抓取同一个页面时不与自身比较
+        assert!(detector.check("http://a.com/1", 0).is_none());
+    }
+
+    #[test]
+    fn rejects_thresholds_outside_zero_to_one() {
+        assert!(DedupConfig { threshold: 1.5, ..DedupConfig::default() }.validate().is_err());
+        assert!(DedupConfig { threshold: -0.1, ..DedupConfig::default() }.validate().is_err());
+        assert!(DedupConfig::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use reqwest::Url;
+use serde::{Serialize, Deserialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use crate::fetcher::client::HttpClient;
+use crate::store::TaskStore;
+// 日志宏
+use log::{info, error};
+
+/// 死链检查，对应任务配置文件中的 `[check_links]` 段，`spider check-links` 创建的任务会开启
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LinkCheckConfig {
+    /// 是否只抓取范围内的页面，并检查指向范围外的链接
+    pub enabled: bool,
+    /// 除种子 URL 的主机外，也在抓取范围内的主机
+    pub hosts: Vec<String>,
+    /// 每个请求的超时秒数，超时的链接记为死链
+    pub timeout_secs: u64,
+    /// 同时检查的范围外链接数
+    pub concurrency: usize,
+}
+
+impl Default for LinkCheckConfig {
+    fn default() -> Self {
+        LinkCheckConfig { enabled: false, hosts: Vec::new(), timeout_secs: 10, concurrency: 16 }
+    }
+}
+
+/// 运行中的死链检查，worker 共用：判断链接是否在抓取范围内，并检查范围外的链接，每个链接只检查一次
+pub struct LinkChecker {
+    hosts: HashSet<String>,
+    checked: Mutex<HashSet<String>>,
+    permits: Arc<Semaphore>,
+    http_client: Arc<HttpClient>,
+    store: Arc<TaskStore>,
+    task_id: u32,
+}
+
+impl LinkChecker {
+    /// 抓取范围为种子 URL 的主机和配置中的主机，`checked` 为之前运行中已检查过的链接
+    pub fn new(
+        config: &LinkCheckConfig,
+        seeds: &[String],
+        checked: HashSet<String>,
+        http_client: Arc<HttpClient>,
+        store: Arc<TaskStore>,
+        task_id: u32,
+    ) -> Self {
+        let hosts = seeds.iter()
+            .filter_map(|seed| Url::parse(seed).ok()?.host_str().map(str::to_string))
+            .chain(config.hosts.iter().cloned())
+            .collect();
+        LinkChecker {
+            hosts,
+            checked: Mutex::new(checked),
+            permits: Arc::new(Semaphore::new(config.concurrency.max(1))),
+            http_client,
+            store,
+            task_id,
+        }
+    }
+
+    /// 链接是否在抓取范围内，范围内的链接照常抓取并跟踪
+    pub fn in_scope(&self, url: &str) -> bool {
+        Url::parse(url).ok().is_some_and(|url| url.host_str().is_some_and(|host| self.hosts.contains(host)))
+    }
+
+    /// 检查范围外的 HTTP 链接：范围内、非 HTTP 和已检查过的链接被忽略，其余链接并发检查并记录结果
+    pub async fn check_async(&self, urls: &[String]) {
+        let pending: Vec<String> = {
+            let mut checked = self.checked.lock().unwrap();
+            urls.iter()
+                .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                .filter(|url| !self.in_scope(url) && checked.insert(url.to_string()))
+                .cloned()
+                .collect()
+        };
+
+        let mut checks = JoinSet::new();
+        for url in pending {
+            let permits = Arc::clone(&self.permits);
+            let http_client = Arc::clone(&self.http_client);
+            checks.spawn(async move {
+                let _permit = permits.acquire_owned().await.unwrap();
+                let result = http_client.check_async(&url).await;
+                (url, result)
+            });
+        }
+        while let Some(joined) = checks.join_next().await {
+            let Ok((url, result)) = joined else {
+                continue;
+            };
+            match &result {
+                Ok(status) if *status >= 400 => info!(url = url.as_str(); "Broken link {}: status {}", url, status),
+                Ok(_) => {}
+                Err(e) => info!(url = url.as_str(); "Broken link {}: {}", url, e),
+            }
+            if let Err(e) = self.store.record_link_check(self.task_id, &url, &result) {
+                error!(url = url.as_str(); "Failed to save link check of {}: {}", url, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Write;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use crate::fetcher::client::{HttpClient, RUNTIME};
+use crate::store::stats::LATENCY_BUCKETS_MS;
+use crate::utils::logging;
+use super::{CrawlMonitor, RunControl, TaskInfo};
+// 日志宏
+use log::{info, debug};
+
+/// 指标的数据来源：worker 更新的任务计数和运行统计
+#[derive(Clone)]
+pub struct MetricsSource {
+    pub task_info: Arc<Mutex<TaskInfo>>,
+    pub monitor: Arc<CrawlMonitor>,
+    pub http_client: Arc<HttpClient>,
+    pub control: Arc<RunControl>,
+}
+
+/// 运行中的指标服务，释放时停止
+pub struct MetricsServer {
+    handle: JoinHandle<()>,
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// 在 `addr` 上以 Prometheus 文本格式提供 `/metrics`
+pub fn serve(addr: SocketAddr, source: MetricsSource) -> io::Result<MetricsServer> {
+    let listener = RUNTIME.block_on(TcpListener::bind(addr))?;
+    info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+    let task_id = source.task_info.lock().unwrap().id;
+    let handle = RUNTIME.spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(logging::in_task(task_id, handle_connection(stream, source.clone())));
+        }
+    });
+    Ok(MetricsServer { handle })
+}
+
+// 每个连接只处理一个请求
+async fn handle_connection(mut stream: TcpStream, source: MetricsSource) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+    let request = String::from_utf8_lossy(&buffer);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or("/"));
+    debug!("Metrics request: {} {}", method, path);
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", render(&source)),
+        ("GET", "/") => ("200 OK", "text/html; charset=utf-8", "<html><body><a href=\"/metrics\">Metrics</a></body></html>".to_string()),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "Not Found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+// 按 Prometheus 文本格式输出全部指标
+fn render(source: &MetricsSource) -> String {
+    let task = source.task_info.lock().unwrap().clone();
+    let stats = source.monitor.stats(source.http_client.retries());
+    let snapshot = source.monitor.snapshot();
+    let mut out = String::new();
+
+    metric(&mut out, "spider_task_info", "gauge", "The running task", &[
+        (format!("{{id=\"{}\",name=\"{}\"}}", task.id, escape(&task.name)), 1.0),
+    ]);
+    metric(&mut out, "spider_paused", "gauge", "Whether the task is paused", &[(String::new(), source.control.is_paused() as u8 as f64)]);
+    metric(&mut out, "spider_fetched_total", "counter", "Pages fetched in this run", &[(String::new(), stats.fetched as f64)]);
+    metric(&mut out, "spider_failed_total", "counter", "URLs that failed in this run", &[(String::new(), stats.failed as f64)]);
+    metric(&mut out, "spider_skipped_total", "counter", "URLs skipped in this run", &[(String::new(), stats.skipped as f64)]);
+    metric(&mut out, "spider_retries_total", "counter", "Requests retried in this run", &[(String::new(), stats.retries as f64)]);
+    metric(&mut out, "spider_downloaded_bytes_total", "counter", "Response body bytes downloaded in this run", &[(String::new(), stats.bytes as f64)]);
+    metric(&mut out, "spider_in_flight", "gauge", "URLs being fetched", &[(String::new(), task.in_flight as f64)]);
+    metric(&mut out, "spider_queue_depth", "gauge", "URLs waiting in the queue", &[(String::new(), task.queued as f64)]);
+    metric(&mut out, "spider_discovered_urls", "gauge", "URLs discovered by the task", &[(String::new(), task.discovered as f64)]);
+    metric(&mut out, "spider_done_urls", "gauge", "URLs done by the task (fetched, failed or skipped)", &[(String::new(), task.progress as f64)]);
+
+    let status_codes: Vec<_> = stats.status_codes.iter()
+        .map(|(code, count)| (format!("{{code=\"{}\"}}", code), *count as f64))
+        .collect();
+    metric(&mut out, "spider_responses_total", "counter", "Fetched pages by HTTP status code", &status_codes);
+
+    let mut host_urls = Vec::new();
+    let mut host_bytes = Vec::new();
+    for (host, counts) in &stats.hosts {
+        let host = escape(host);
+        for (result, count) in [("fetched", counts.fetched), ("failed", counts.failed), ("skipped", counts.skipped)] {
+            host_urls.push((format!("{{host=\"{}\",result=\"{}\"}}", host, result), count as f64));
+        }
+        host_bytes.push((format!("{{host=\"{}\"}}", host), counts.bytes as f64));
+    }
+    metric(&mut out, "spider_host_urls_total", "counter", "URLs done in this run by host and result", &host_urls);
+    metric(&mut out, "spider_host_downloaded_bytes_total", "counter", "Response body bytes downloaded in this run by host", &host_bytes);
+    let intervals: Vec<_> = snapshot.host_intervals.iter()
+        .map(|(host, interval)| (format!("{{host=\"{}\"}}", escape(host)), interval.as_secs_f64()))
+        .collect();
+    metric(&mut out, "spider_host_request_interval_seconds", "gauge", "Average time between two requests to a host", &intervals);
+
+    // 直方图的桶是累计计数
+    let (buckets, sum_ms) = stats.latency_histogram();
+    let name = "spider_fetch_duration_seconds";
+    let _ = writeln!(out, "# HELP {} Time to fetch a page", name);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound_ms, count) in LATENCY_BUCKETS_MS.iter().zip(buckets) {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, *bound_ms as f64 / 1000.0, count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, stats.fetched);
+    let _ = writeln!(out, "{}_sum {}", name, sum_ms as f64 / 1000.0);
+    let _ = writeln!(out, "{}_count {}", name, stats.fetched);
+    out
+}
+
+// 输出一个指标的说明、类型和各标签组合的值，`samples` 中的标签已带花括号
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(String, f64)]) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+// 转义标签值中的反斜杠、双引号和换行
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -1,6 +1,24 @@
+pub mod async_worker;  // 导入 async_worker.rs 文件
+pub mod budget;  // 导入 budget.rs 文件
+pub mod control;  // 导入 control.rs 文件
+pub mod dashboard;  // 导入 dashboard.rs 文件
+pub mod dedup;  // 导入 dedup.rs 文件
+pub mod link_check;  // 导入 link_check.rs 文件
+pub mod metrics;  // 导入 metrics.rs 文件
+pub mod monitor;  // 导入 monitor.rs 文件
+pub mod run_lock;  // 导入 run_lock.rs 文件
+pub mod schedule;  // 导入 schedule.rs 文件
 pub mod task;  // 导入 task.rs 文件
-pub mod worker;  // 导入 worker.rs 文件
+pub mod thread_worker;  // 导入 thread_worker.rs 文件
 
 // 确保 TaskManager 和 TaskStatus 从 task 模块中导出
-pub use self::task::{TaskManager, TaskStatus, TaskInfo};
-pub use self::worker::{Worker};
+pub use self::task::{ListOptions, ListSort, OutputFormat, TaskManager, TaskStatus, TaskInfo, UrlOutcome};
+pub use self::thread_worker::{Worker};
+pub use self::async_worker::{AsyncWorker, EngineConfig, EngineMode};
+pub use self::budget::{Budget, BudgetConfig};
+pub use self::control::RunControl;
+pub use self::dashboard::Dashboard;
+pub use self::dedup::{DedupConfig, Duplicate, DuplicateDetector};
+pub use self::link_check::{LinkCheckConfig, LinkChecker};
+pub use self::monitor::{CrawlMonitor, MonitorSnapshot};
+pub use self::schedule::{RunTrigger, ScheduleConfig};
@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use reqwest::Url;
+use crate::store::{CrawlStats, FetchRecord};
+
+// 进行中的一个请求
+struct Request {
+    worker: String,
+    url: String,
+    started: Instant,
+}
+
+// 一个主机的请求间隔统计
+#[derive(Default)]
+struct HostActivity {
+    last_request: Option<Instant>,
+    total_interval: Duration,
+    intervals: u32,
+}
+
+struct MonitorState {
+    in_flight: Vec<Request>,
+    hosts: HashMap<String, HostActivity>,
+    stats: CrawlStats,
+}
+
+/// 运行中任务的实时状态和本次运行的统计，由 worker 在请求开始和结束时更新，仪表盘定期读取
+pub struct CrawlMonitor {
+    state: Mutex<MonitorState>,
+}
+
+/// 某一时刻的实时状态
+pub struct MonitorSnapshot {
+    /// 进行中的请求：worker、URL 和已用时间，按 worker 排序
+    pub in_flight: Vec<(String, String, Duration)>,
+    /// 本次运行中各 HTTP 状态码的页面数
+    pub status_codes: BTreeMap<u16, u32>,
+    /// 各主机相邻两次请求的平均间隔，即实际的礼貌延迟
+    pub host_intervals: HashMap<String, Duration>,
+}
+
+impl CrawlMonitor {
+    pub fn new(task_id: u32) -> Self {
+        let state = MonitorState { in_flight: Vec::new(), hosts: HashMap::new(), stats: CrawlStats::new(task_id) };
+        CrawlMonitor { state: Mutex::new(state) }
+    }
+
+    /// worker 开始请求 URL
+    pub fn start(&self, worker: &str, url: &str) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if let Some(host) = Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)) {
+            let host = state.hosts.entry(host).or_default();
+            if let Some(last) = host.last_request.replace(now) {
+                host.total_interval += now - last;
+                host.intervals += 1;
+            }
+        }
+        state.in_flight.push(Request { worker: worker.to_string(), url: url.to_string(), started: now });
+    }
+
+    /// 页面抓取成功
+    pub fn fetched(&self, record: &FetchRecord) {
+        let mut state = self.finish(&record.url);
+        state.stats.record_fetch(record);
+    }
+
+    /// URL 抓取或解析失败
+    pub fn failed(&self, url: &str, depth: u32) {
+        let mut state = self.finish(url);
+        state.stats.record_failure(url, depth);
+    }
+
+    /// URL 被跳过
+    pub fn skipped(&self, url: &str, depth: u32, reason: &str) {
+        let mut state = self.finish(url);
+        state.stats.record_skip(url, depth, reason);
+    }
+
+    /// 截至目前的运行统计，`retries` 为 HTTP 客户端重试的请求数
+    pub fn stats(&self, retries: u32) -> CrawlStats {
+        let mut stats = self.state.lock().unwrap().stats.clone();
+        stats.retries = retries;
+        stats.finish();
+        stats
+    }
+
+    // 把 URL 移出进行中的请求
+    fn finish(&self, url: &str) -> MutexGuard<'_, MonitorState> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = state.in_flight.iter().position(|request| request.url == url) {
+            state.in_flight.swap_remove(index);
+        }
+        state
+    }
+
+    pub fn snapshot(&self) -> MonitorSnapshot {
+        let state = self.state.lock().unwrap();
+        let mut in_flight: Vec<_> = state.in_flight.iter()
+            .map(|request| (request.worker.clone(), request.url.clone(), request.started.elapsed()))
+            .collect();
+        in_flight.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)));
+        let host_intervals = state.hosts.iter()
+            .filter(|(_, activity)| activity.intervals > 0)
+            .map(|(host, activity)| (host.clone(), activity.total_interval / activity.intervals))
+            .collect();
+        MonitorSnapshot { in_flight, status_codes: state.stats.status_codes.clone(), host_intervals }
+    }
+}
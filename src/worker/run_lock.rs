@@ -0,0 +1,72 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// 运行锁文件名，保存在任务目录中
+pub const LOCK_FILE: &str = "run.lock";
+
+/// 任务运行锁：运行期间在任务目录中保存当前进程的 PID，释放时删除
+pub struct RunLock {
+    path: PathBuf,
+}
+
+impl RunLock {
+    /// 获取任务目录的运行锁，已被其他存活进程持有时返回错误；只有确认持有进程已退出时才清理它留下的锁，然后重试一次
+    pub fn acquire(task_dir: &str) -> io::Result<Self> {
+        fs::create_dir_all(task_dir)?;
+        let path = Path::new(task_dir).join(LOCK_FILE);
+        match Self::create(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            result => return result,
+        }
+        match fs::read_to_string(&path).ok().and_then(|pid| pid.trim().parse::<u32>().ok()) {
+            Some(pid) if process_alive(pid) => {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, format!("task is already running in process {}", pid)));
+            }
+            Some(_) => fs::remove_file(&path)?,
+            None => {
+                let message = format!("lock file {} is unreadable, remove it if no process is running the task", path.display());
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, message));
+            }
+        }
+        Self::create(&path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => io::Error::new(io::ErrorKind::WouldBlock, "task was started by another process"),
+            _ => e,
+        })
+    }
+
+    // 先把 PID 写入临时文件，再用硬链接原子地创建锁文件，锁文件已存在时失败；其他进程读到的锁文件总是包含完整的 PID
+    fn create(path: &Path) -> io::Result<Self> {
+        let tmp = path.with_extension(format!("lock.{}.tmp", std::process::id()));
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&tmp)?;
+        write!(file, "{}", std::process::id())?;
+        drop(file);
+        let linked = fs::hard_link(&tmp, path);
+        let _ = fs::remove_file(&tmp);
+        linked.map(|_| RunLock { path: path.to_path_buf() })
+    }
+
+    /// 持有任务运行锁的存活进程 PID
+    pub fn holder(task_dir: &str) -> Option<u32> {
+        let pid = fs::read_to_string(Path::new(task_dir).join(LOCK_FILE)).ok()?.trim().parse().ok()?;
+        process_alive(pid).then_some(pid)
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // 信号 0 只检查进程是否存在；EPERM 说明进程存在但属于其他用户
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
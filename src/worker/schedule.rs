@@ -0,0 +1,88 @@
+use chrono::{DateTime, Local, Utc};
+use croner::Cron;
+use serde::{Serialize, Deserialize};
+
+/// 任务计划，对应任务配置文件中的 `[schedule]` 段，由 `spider serve` 按计划重新运行任务
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    /// cron 表达式（分 时 日 月 周，按本地时间），例如 `0 3 * * *` 表示每天 3 点，也可以用 `@daily`、`@hourly` 等
+    pub cron: Option<String>,
+}
+
+impl ScheduleConfig {
+    /// 检查 cron 表达式是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.cron {
+            Some(cron) => parse_cron(cron).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// `after` 之后下一次运行的时间，没有计划或表达式无效时为 None
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let cron = parse_cron(self.cron.as_deref()?).ok()?;
+        let next = cron.find_next_occurrence(&after.with_timezone(&Local), false).ok()?;
+        Some(next.with_timezone(&Utc))
+    }
+}
+
+/// 解析 cron 表达式，用于命令行参数校验
+pub fn parse_cron(cron: &str) -> Result<Cron, String> {
+    Cron::new(cron).parse().map_err(|e| format!("invalid cron expression {}: {}", cron, e))
+}
+
+/// 一次运行的触发方式，记录在运行历史中
+#[derive(Clone, Copy, PartialEq)]
+pub enum RunTrigger {
+    /// 命令行或 API 启动的运行
+    Manual,
+    /// 按任务计划启动的运行
+    Schedule,
+}
+
+impl RunTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunTrigger::Manual => "manual",
+            RunTrigger::Schedule => "schedule",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use super::*;
+
+    fn schedule(cron: &str) -> ScheduleConfig {
+        ScheduleConfig { cron: Some(cron.to_string()) }
+    }
+
+    #[test]
+    fn finds_the_next_run_after_a_time() {
+        // 每 15 分钟一次，与本地时区的偏移无关
+        let after = Utc.with_ymd_and_hms(2024, 5, 1, 10, 7, 30).unwrap();
+        assert_eq!(schedule("*/15 * * * *").next_run(after), Some(Utc.with_ymd_and_hms(2024, 5, 1, 10, 15, 0).unwrap()));
+        let on_time = Utc.with_ymd_and_hms(2024, 5, 1, 10, 15, 0).unwrap();
+        assert_eq!(schedule("*/15 * * * *").next_run(on_time), Some(Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap()));
+    }
+
+    #[test]
+    fn daily_runs_are_a_day_apart() {
+        let after = Utc.with_ymd_and_hms(2024, 5, 1, 10, 7, 30).unwrap();
+        let first = schedule("@daily").next_run(after).unwrap();
+        let second = schedule("@daily").next_run(first).unwrap();
+        assert!(first > after && first - after <= chrono::Duration::days(1));
+        assert_eq!(second - first, chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn no_next_run_without_a_valid_schedule() {
+        let now = Utc::now();
+        assert_eq!(ScheduleConfig::default().next_run(now), None);
+        assert_eq!(schedule("not a cron").next_run(now), None);
+        assert!(schedule("not a cron").validate().is_err());
+        assert!(schedule("0 3 * * *").validate().is_ok());
+    }
+}
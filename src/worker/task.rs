@@ -1,51 +1,168 @@
 use std::collections::{HashSet, VecDeque};
-use std::fs;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{self, BufRead, Write};
+use std::net::SocketAddr;
 use std::path::Path;
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
-use std::thread::{self, JoinHandle};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::fmt;
-use crossbeam::channel;
+use std::time::{Duration, Instant};
+use crossbeam::channel::{self, RecvTimeoutError};
 use serde::{Serialize, Deserialize};
-use chrono::Utc;
-use super::Worker;
+use chrono::{DateTime, Utc};
+use super::{AsyncWorker, Budget, BudgetConfig, CrawlMonitor, Dashboard, DuplicateDetector, EngineMode, LinkChecker, RunControl, RunTrigger, ScheduleConfig, Worker};
+use super::control;
+use super::metrics::{self, MetricsSource};
+use super::run_lock::RunLock;
 use crate::fetcher::client::HttpClient;
-use prettytable::{cell, row, Table};
+use crate::fetcher::{CacheStats, HttpCache};
+use crate::store::{validate_task_name, Checkpoint, CreateOutcome, CrawlStats, GraphFormat, RunDiff, RunRecord, TaskRef, TaskReport, TaskStore, DEFAULT_STORE_PATH};
+use crate::store::archive::{self, Unpacked};
+use crate::store::report::{print_broken_links, print_duplicates, write_broken_links, BrokenLink, Outputs};
+use crate::store::checkpoint::CHECKPOINT_FILE;
+use crate::utils::TaskConfig;
+use crate::utils::logging;
+use crate::utils::output::{output_mode, OutputMode};
+use crate::utils::disk::dir_size;
+use crate::utils::format::format_bytes;
+use prettytable::{row, Table};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-// 引入宏
-use crate::info;
-use crate::warn;
-use crate::error;
-use crate::TERMINATE;
+// 日志宏
+use log::{info, warn, error};
 // use crate::utils::logging;
 // use crate::utils::logging::{info, warn, error};
 
+// 非交互模式下输出状态行的间隔
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TaskInfo {
     pub id: u32,
     pub name: String,
+    /// 已处理完成（抓取成功、失败或被跳过）的 URL 数
     pub progress: u32,
+    /// 已发现的 URL 总数，即已完成、正在抓取和待抓取的 URL 之和
+    #[serde(default)]
+    pub discovered: u32,
+    /// 待抓取队列中尚未开始抓取的 URL 数
+    #[serde(default)]
+    pub queued: u32,
+    /// 正在抓取的 URL 数
+    #[serde(default)]
+    pub in_flight: u32,
+    /// 成功抓取的页面数
+    #[serde(default)]
+    pub fetched: u32,
+    /// 抓取或解析失败的 URL 数
+    #[serde(default)]
+    pub failed: u32,
     pub deep: u32,
     pub status: TaskStatus,
     pub created_at: Option<String>,
     pub ended_at: Option<String>,
+    /// 任务提前结束的原因，例如抓取预算用尽
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    /// 最近一次运行中页面相对 HTTP 缓存的变化统计
+    #[serde(default)]
+    pub cache: Option<CacheStats>,
+    /// 按下载限制跳过的 URL 数
+    #[serde(default)]
+    pub skipped: u32,
+    /// 超过大小限制被截断的页面数
+    #[serde(default)]
+    pub truncated: u32,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub enum TaskStatus {
     Idle,
     Running,
+    Paused,
     Finished,
     Stopped,
 }
 
+impl TaskInfo {
+    pub fn new(name: String, deep: u32) -> Self {
+        TaskInfo {
+            id: 0,
+            name,
+            deep,
+            progress: 0,
+            discovered: 0,
+            queued: 0,
+            in_flight: 0,
+            fetched: 0,
+            failed: 0,
+            created_at: None,
+            ended_at: None,
+            finish_reason: None,
+            cache: None,
+            skipped: 0,
+            truncated: 0,
+            status: TaskStatus::Idle,
+        }
+    }
+
+    /// 开始抓取一个待抓取队列中的 URL
+    pub fn start_url(&mut self) {
+        self.in_flight += 1;
+        self.update_totals();
+    }
+
+    /// 一个 URL 处理完成，`discovered` 为该页面新发现并加入待抓取队列的 URL 数
+    pub fn finish_url(&mut self, outcome: UrlOutcome, discovered: u32) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        match outcome {
+            UrlOutcome::Fetched => self.fetched += 1,
+            UrlOutcome::Failed => self.failed += 1,
+            UrlOutcome::Skipped => self.skipped += 1,
+        }
+        self.discovered += discovered;
+        self.update_totals();
+    }
+
+    /// 用任务存储中的记录重新计算各项计数，`queued` 为待抓取队列的长度
+    pub fn reset_counters(&mut self, fetched: u32, failed: u32, skipped: u32, queued: u32) {
+        self.fetched = fetched;
+        self.failed = failed;
+        self.skipped = skipped;
+        self.in_flight = 0;
+        self.discovered = fetched + failed + skipped + queued;
+        self.update_totals();
+    }
+
+    /// 从 worker 发来的任务信息中复制进度计数
+    pub fn copy_progress(&mut self, other: &TaskInfo) {
+        self.progress = other.progress;
+        self.discovered = other.discovered;
+        self.queued = other.queued;
+        self.in_flight = other.in_flight;
+        self.fetched = other.fetched;
+        self.failed = other.failed;
+        self.skipped = other.skipped;
+    }
+
+    fn update_totals(&mut self) {
+        self.progress = self.fetched + self.failed + self.skipped;
+        self.queued = self.discovered.saturating_sub(self.progress + self.in_flight);
+    }
+}
+
+/// 一个 URL 的处理结果
+pub enum UrlOutcome {
+    Fetched,
+    Failed,
+    Skipped,
+}
+
 // 为 TaskStatus 实现 Display trait
 impl fmt::Display for TaskStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let status = match self {
             TaskStatus::Idle => "Idle",
             TaskStatus::Running => "Running",
+            TaskStatus::Paused => "Paused",
             TaskStatus::Finished => "Finished",
             TaskStatus::Stopped => "Stopped",
         };
@@ -53,67 +170,311 @@ impl fmt::Display for TaskStatus {
     }
 }
 
+impl std::str::FromStr for TaskStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "idle" => Ok(TaskStatus::Idle),
+            "running" => Ok(TaskStatus::Running),
+            "paused" => Ok(TaskStatus::Paused),
+            "finished" => Ok(TaskStatus::Finished),
+            "stopped" => Ok(TaskStatus::Stopped),
+            _ => Err(format!("unknown task status {}", value)),
+        }
+    }
+}
+
+/// `list` 的排序字段
+#[derive(Clone, Copy, PartialEq)]
+pub enum ListSort {
+    Created,
+    Name,
+    Progress,
+}
+
+/// 命令行输出格式
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// `list` 的过滤、排序和输出选项
+pub struct ListOptions {
+    pub status: Option<TaskStatus>,
+    /// 只列出在此时间之后创建的任务
+    pub since: Option<DateTime<Utc>>,
+    pub sort: ListSort,
+    pub reverse: bool,
+    pub limit: Option<usize>,
+    pub format: OutputFormat,
+}
+
+/// `list --format json` 输出的一条记录
+#[derive(Serialize)]
+pub struct TaskListEntry {
+    #[serde(flatten)]
+    pub task: TaskInfo,
+    /// 任务目录占用的字节数
+    pub disk_usage: u64,
+}
+
 #[derive(Clone)]
 pub struct TaskManager {
     visited: HashSet<String>,
-    to_visit: VecDeque<String>,
+    // 待抓取的 URL 及其深度
+    to_visit: VecDeque<(String, u32)>,
     task_info: TaskInfo,
     task_dir: String,
+    config: TaskConfig,
+    store: Arc<TaskStore>,
+    // 最近一次运行的统计
+    stats: Option<CrawlStats>,
+    control: Arc<RunControl>,
+    // 记录在运行历史中的触发方式
+    trigger: RunTrigger,
 }
 
 impl TaskManager {
     pub fn new() -> Self {
+        Self::with_store(Arc::new(TaskStore::open(DEFAULT_STORE_PATH).expect("Failed to open task store")))
+    }
+
+    /// 使用已打开的任务存储，同一进程中的多个任务共用一个数据库连接
+    pub fn with_store(store: Arc<TaskStore>) -> Self {
         TaskManager {
             visited: HashSet::new(),
             to_visit: VecDeque::new(),
             task_dir: String::new(),
-            task_info: TaskInfo {
-                id: 0,
-                name: String::new(),
-                deep: 0,
-                progress: 0,
-                created_at: None,
-                ended_at: None,
-                status: TaskStatus::Idle,
-            },
+            config: TaskConfig::default(),
+            task_info: TaskInfo::new(String::new(), 0),
+            store,
+            stats: None,
+            control: Arc::new(RunControl::default()),
+            trigger: RunTrigger::Manual,
         }
     }
 
-    // New method to handle setting up and saving a task
-    pub fn create_task(&mut self, start_urls: Vec<String>, task_name: String, deep: u32) {
-        self.task_info.id = Self::generate_task_id();
+    /// 创建任务并分配唯一 ID。任务名无效或已存在时返回错误，`force` 为 true 时替换同名任务（保留其 HTTP 缓存）
+    pub fn create_task(&mut self, start_urls: Vec<String>, task_name: String, deep: u32, config: TaskConfig, force: bool) -> Result<(), String> {
+        validate_task_name(&task_name)?;
         self.task_info.name = task_name.clone();
         self.task_info.deep = deep;
         self.task_info.created_at = Some(Self::current_timestamp());
-        self.to_visit = VecDeque::from(start_urls);
+
+        // 检查任务名和写入任务在同一事务中完成
+        match self.store.create_task(&self.task_info, &config, &start_urls, force).map_err(|e| format!("Failed to save task: {}", e))? {
+            CreateOutcome::NameTaken(existing_id) => {
+                return Err(format!("Task named {} already exists (ID {}), use --force to replace it", task_name, existing_id));
+            }
+            CreateOutcome::Created { id, replaced } => {
+                if let Some(replaced_id) = replaced {
+                    info!("Replaced task {} ({})", replaced_id, task_name);
+                    let _ = std::fs::remove_file(format!("tasks/{}/{}", task_name, CHECKPOINT_FILE));
+                }
+                self.task_info.id = id;
+            }
+        }
+
+        self.to_visit = start_urls.iter().map(|url| (url.clone(), 1)).collect();
         self.task_dir = format!("tasks/{}", self.task_info.name);
+        self.config = config;
 
+        // 任务目录用于存放 HTTP 缓存等文件
         std::fs::create_dir_all(&self.task_dir).expect("Failed to create task directory");
 
-        // Save task information to a file
-        Self::save_task_info(&self.task_info, &self.task_dir);
+        self.load_progress();
+        self.save_task();
+        Ok(())
+    }
+
+    /// 从任务存储中加载任务信息、已访问集合和待抓取队列，任务不存在时返回 false
+    pub fn load_task(&mut self, task_id: u32) -> bool {
+        let Some((task_info, config)) = self.store.load_task(task_id).expect("Failed to load task") else {
+            return false;
+        };
+        self.task_info = task_info;
+        self.config = config;
+        self.task_dir = self.get_task_dir();
+        self.visited = self.store.load_visited(task_id).expect("Failed to load visited URLs");
+        self.to_visit = self.store.load_frontier(task_id).expect("Failed to load frontier").into();
+        self.load_progress();
+        true
+    }
+
+    /// 运行控制，用于从其他线程停止运行中的任务
+    pub fn run_control(&self) -> Arc<RunControl> {
+        Arc::clone(&self.control)
+    }
+
+    /// 设置下一次运行的触发方式，默认为手动运行
+    pub fn set_trigger(&mut self, trigger: RunTrigger) {
+        self.trigger = trigger;
+    }
+
+    /// 当前加载的任务信息
+    pub fn task_info(&self) -> &TaskInfo {
+        &self.task_info
+    }
+
+    /// 用 `overrides` 中设置的项更新任务的抓取预算并保存
+    pub fn update_budget(&mut self, overrides: &BudgetConfig) {
+        self.config.budget.merge(overrides);
+        self.store.save_config(self.task_info.id, &self.config).expect("Failed to save task config");
     }
 
-    pub fn run(&mut self, m: &Arc<MultiProgress>) {
+    // 以任务存储中的记录为准重新计算进度计数，修正中断时未保存的计数
+    fn load_progress(&mut self) {
+        let (fetched, failed, skipped, queued) = self.store.count_progress(self.task_info.id).expect("Failed to count task progress");
+        self.task_info.reset_counters(fetched, failed, skipped, queued);
+    }
+
+    /// 恢复中断的任务：任务存储中缺少该任务或无法读取时从最近的检查点恢复，
+    /// 并把中断时正在抓取的 URL 重新放回待抓取队列。任务和检查点都不存在时返回 false
+    pub fn recover(&mut self, task: &TaskRef) -> bool {
+        let stored = self.store.resolve(task).and_then(|task_id| match task_id {
+            Some(task_id) => self.store.load_task(task_id).map(|loaded| loaded.map(|_| task_id)),
+            None => Ok(None),
+        });
+        let task_id = match stored {
+            Ok(Some(task_id)) => task_id,
+            stored => {
+                if let Err(e) = stored {
+                    warn!("Failed to load task {} from store: {}", task, e);
+                }
+                let Some(checkpoint) = Checkpoint::find("tasks", task) else {
+                    return false;
+                };
+                warn!("Restoring task {} from checkpoint saved at {}", task, checkpoint.saved_at);
+                self.store.restore(&checkpoint).expect("Failed to restore task from checkpoint");
+                checkpoint.task_info.id
+            }
+        };
+
+        let requeued = self.store.requeue_in_flight(task_id).expect("Failed to requeue in-flight URLs");
+        if requeued > 0 {
+            info!("Requeued {} URLs that were in flight when task {} was interrupted", requeued, task_id);
+        }
+        self.load_task(task_id)
+    }
+
+    /// 清空任务进度并从种子 URL 重新开始，任务不存在时返回 false
+    pub fn reset_task(&mut self, task: &TaskRef) -> bool {
+        let Some(task_id) = self.store.resolve(task).expect("Failed to look up task") else {
+            return false;
+        };
+        self.store.reset_task(task_id).expect("Failed to reset task");
+        self.load_task(task_id);
+        self.task_info.progress = 0;
+        self.task_info.status = TaskStatus::Idle;
+        self.task_info.ended_at = None;
+        self.save_task();
+        true
+    }
+
+    /// 运行任务直到完成或被中断，任务已在其他进程中运行时返回错误；`metrics_addr` 不为空时在该地址提供 Prometheus 指标
+    pub fn run(&mut self, m: &Arc<MultiProgress>, metrics_addr: Option<SocketAddr>) -> Result<(), String> {
+        let task_dir = self.get_task_dir();
+        let _run_lock = RunLock::acquire(&task_dir)
+            .map_err(|e| format!("Cannot run task {} ({}): {}", self.task_info.id, self.task_info.name, e))?;
+        let _task_log = logging::open_task_log(self.task_info.id, &task_dir)
+            .map_err(|e| error!("Failed to open log file for task {}: {}", self.task_info.id, e))
+            .ok();
+        info!("Running task {} ({})", self.task_info.id, self.task_info.name);
+        // 清理上次运行遗留的暂停控制文件
+        if let Err(e) = control::set_paused(&task_dir, false) {
+            error!("Failed to remove pause file: {}", e);
+        }
+
+        if self.to_visit.is_empty() {
+            info!("Nothing left to crawl for task {}", self.task_info.name);
+            self.task_info.status = TaskStatus::Finished;
+            self.task_info.ended_at = Some(Self::current_timestamp());
+            self.save_task();
+            return Ok(());
+        }
 
         self.task_info.status = TaskStatus::Running;
+        self.task_info.finish_reason = None;
+        self.save_task();
+        // 记录本次运行，结束时写入状态和统计
+        let run_id = self.store.start_run(self.task_info.id, self.trigger.as_str())
+            .map_err(|e| error!("Failed to record run of task {}: {}", self.task_info.id, e))
+            .ok();
 
         let num_threads = num_cpus::get();
         let (sender, receiver) = channel::unbounded();
 
         // 计算每个 worker 应处理的 URL 数量
-        let chunk_size = (self.to_visit.len() + num_threads - 1) / num_threads;
+        let chunk_size = self.to_visit.len().div_ceil(num_threads);
+
+        let mut http_client = HttpClient::new(None, self.config.auth.clone())
+            .with_download_config(self.config.download.clone());
+        if self.config.check_links.enabled {
+            http_client = http_client.with_timeout(Duration::from_secs(self.config.check_links.timeout_secs));
+        }
+        if self.config.cache.enabled {
+            match HttpCache::new(&format!("{}/cache", self.get_task_dir())) {
+                Ok(cache) => http_client = http_client.with_cache(cache),
+                Err(e) => error!("Failed to open HTTP cache: {}", e),
+            }
+        }
+        let http_client = Arc::new(http_client);
+
+        // 需要表单登录的站点在分发种子 URL 之前先完成登录
+        if let Err(e) = http_client.login() {
+            error!("Login failed: {}", e);
+            self.task_info.status = TaskStatus::Stopped;
+            self.task_info.ended_at = Some(Self::current_timestamp());
+            self.save_task();
+            let mut stats = CrawlStats::new(self.task_info.id);
+            stats.finish();
+            self.finish_run(run_id, &stats);
+            return Ok(());
+        }
 
         let visited = Arc::new(Mutex::new(self.visited.clone()));
         let task_info = Arc::new(Mutex::new(self.task_info.clone()));
-        let http_client = Arc::new(HttpClient::new(None));
+        let fetched = self.store.load_fetched_sizes(self.task_info.id).expect("Failed to load fetched pages");
+        let budget = Arc::new(Budget::new(self.config.budget.clone(), &fetched));
+        let monitor = Arc::new(CrawlMonitor::new(self.task_info.id));
+        let fingerprints = self.store.load_fingerprints(self.task_info.id).expect("Failed to load page fingerprints");
+        let dedup = Arc::new(DuplicateDetector::new(self.config.dedup.clone(), fingerprints));
+        let link_checker = if self.config.check_links.enabled {
+            let seeds = self.store.load_seeds(self.task_info.id).expect("Failed to load seeds");
+            let checked = self.store.load_checked_links(self.task_info.id).expect("Failed to load checked links");
+            Some(Arc::new(LinkChecker::new(
+                &self.config.check_links,
+                &seeds,
+                checked,
+                Arc::clone(&http_client),
+                Arc::clone(&self.store),
+                self.task_info.id,
+            )))
+        } else {
+            None
+        };
+        let _metrics_server = match metrics_addr {
+            Some(addr) => {
+                let source = MetricsSource {
+                    task_info: Arc::clone(&task_info),
+                    monitor: Arc::clone(&monitor),
+                    http_client: Arc::clone(&http_client),
+                    control: Arc::clone(&self.control),
+                };
+                Some(metrics::serve(addr, source).map_err(|e| format!("Cannot serve metrics on {}: {}", addr, e))?)
+            }
+            None => None,
+        };
 
-        // 创建一个新的进度条，长度根据任务总数调整
-        // let pb = ProgressBar::new(self.to_visit.len() as u64);
-        let pb = m.add(ProgressBar::new(self.to_visit.len() as u64));
+        // 创建一个新的进度条，长度为已发现的 URL 数，随待抓取队列增长
+        let pb = m.add(ProgressBar::new(self.task_info.discovered as u64));
+        pb.set_position(self.task_info.progress as u64);
         pb.set_style(
             ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}")
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} ({per_sec}, ETA {eta}) {msg}")
                 .unwrap()  // 确保 template 调用成功，否则会导致 panic
                 .progress_chars("#>-"),
         );
@@ -124,88 +485,265 @@ impl TaskManager {
 
         // 创建 worker 线程
         let mut handles = Vec::new();
-        for chunk in to_visit_vec.chunks(chunk_size) {
-            let worker_to_visit = VecDeque::from(chunk.to_vec()); // 将 chunk 转换为 VecDeque
+        match self.config.engine.mode {
+            EngineMode::Async => {
+                // 异步引擎在单独的线程中驱动，所有请求共享一个调度队列
+                let worker = AsyncWorker::new(
+                    Arc::clone(&http_client),
+                    to_visit_vec,
+                    Arc::clone(&visited),
+                    Arc::clone(&task_info),
+                    sender.clone(),
+                    Arc::clone(&self.store),
+                    Arc::clone(&budget),
+                    Arc::clone(&monitor),
+                    Arc::clone(&self.control),
+                    Arc::clone(&dedup),
+                    link_checker.clone(),
+                    self.config.engine.concurrency,
+                );
+                handles.push(thread::spawn(move || {
+                    worker.run();
+                }));
+            }
+            EngineMode::Thread => {
+                for (index, chunk) in to_visit_vec.chunks(chunk_size).enumerate() {
+                    let worker_to_visit = VecDeque::from(chunk.to_vec()); // 将 chunk 转换为 VecDeque
 
-            let worker = Worker::new(
-                Arc::clone(&http_client),
-                Arc::new(Mutex::new(worker_to_visit)),
-                Arc::clone(&visited),
-                Arc::clone(&task_info),
-                sender.clone(),
-            );
-
-            let handle = thread::spawn(move || {
-                worker.run();
-            });
+                    let worker = Worker::new(
+                        Arc::clone(&http_client),
+                        Arc::new(Mutex::new(worker_to_visit)),
+                        Arc::clone(&visited),
+                        Arc::clone(&task_info),
+                        sender.clone(),
+                        Arc::clone(&self.store),
+                        Arc::clone(&budget),
+                        Arc::clone(&monitor),
+                        Arc::clone(&self.control),
+                        Arc::clone(&dedup),
+                        link_checker.clone(),
+                    );
+
+                    // 线程名用于仪表盘和日志文件中区分 worker
+                    let handle = thread::Builder::new()
+                        .name(format!("worker-{}", index + 1))
+                        .spawn(move || {
+                            worker.run();
+                        })
+                        .expect("Failed to spawn worker thread");
 
-            handles.push(handle);
+                    handles.push(handle);
+                }
+            }
         }
         info!("Thread Number {:?}", handles.len());
+        // 所有 worker 退出后通道断开，主线程据此判断抓取结束
+        drop(sender);
+
+        // 主线程处理任务保存、定期检查点和状态检查
+        let checkpoint_config = self.config.checkpoint.clone();
+        let shutdown_timeout = Duration::from_secs(self.config.engine.shutdown_timeout_secs);
+        let mut last_checkpoint = (Instant::now(), self.task_info.progress);
+        let mut stopping_since = None;
+        let mut drained = true;
+        // 非交互模式下定期输出一行状态代替进度条
+        let plain_status = output_mode() == OutputMode::Plain;
+        let started = (Instant::now(), self.task_info.progress);
+        let mut last_status = Instant::now();
+        if plain_status {
+            self.print_status(started);
+        }
+        // 仪表盘模式下缩短等待间隔，及时响应按键
+        let mut dashboard = None;
+        if output_mode() == OutputMode::Dashboard {
+            let concurrency = match self.config.engine.mode {
+                EngineMode::Async => self.config.engine.concurrency,
+                EngineMode::Thread => handles.len(),
+            };
+            match Dashboard::open(&task_dir, Arc::clone(&self.control), self.config.engine.mode, concurrency, self.task_info.progress) {
+                Ok(opened) => dashboard = Some(opened),
+                Err(e) => eprintln!("Failed to open dashboard: {}", e),
+            }
+        }
+        let tick = if dashboard.is_some() { Duration::from_millis(200) } else { Duration::from_secs(1) };
+        loop {
+            if let Some(dashboard) = dashboard.as_mut() {
+                dashboard.update(&self.task_info, &monitor, &self.store);
+            }
 
-        // 主线程处理任务保存和状态检查
-        while self.task_info.status == TaskStatus::Running {
-            // 从 receiver 接收更新的任务信息
-            if let Ok(updated_task_info) = receiver.recv() {
-                self.task_info.progress = updated_task_info.progress;
-                // 更新进度条
-                // pb.inc(1);
-                pb.set_position(self.task_info.progress as u64);
-                // pb.set_position((self.task_info.progress / self.to_visit.len() * 100).into());
-                pb.set_message(format!("Processing item {}", self.task_info.progress));
-                Self::save_task_info(&self.task_info, &self.get_task_dir());
-                if self.task_info.progress == (self.to_visit.len() as u32) {
-                    break;
+            if plain_status && last_status.elapsed() >= STATUS_INTERVAL {
+                self.print_status(started);
+                last_status = Instant::now();
+            }
+
+            // 收到终止信号后 worker 不再调度新的 URL，这里等待进行中的请求完成，超时则取消进行中的请求
+            if self.control.is_stopping() {
+                let since = *stopping_since.get_or_insert_with(|| {
+                    pb.set_message(format!("Stopping, waiting for {} in-flight requests...", self.task_info.in_flight));
+                    Instant::now()
+                });
+                if drained && since.elapsed() >= shutdown_timeout {
+                    let in_flight = task_info.lock().unwrap().in_flight;
+                    warn!("Cancelling {} in-flight requests, they will be retried on resume", in_flight);
+                    pb.set_message(format!("Cancelling {} in-flight requests...", in_flight));
+                    self.control.cancel();
+                    drained = false;
+                }
+            } else {
+                // 按控制文件和 SIGUSR1 / SIGUSR2 信号切换暂停状态
+                match self.control.poll(&task_dir) {
+                    Ok(paused) if paused != (self.task_info.status == TaskStatus::Paused) => {
+                        self.task_info.status = if paused { TaskStatus::Paused } else { TaskStatus::Running };
+                        info!("Task {} {}", self.task_info.name, if paused { "paused" } else { "resumed" });
+                        self.update_progress_bar(&pb);
+                        self.save_task();
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to update pause state: {}", e),
                 }
             }
 
-            // 处理终止信号
-            if TERMINATE.load(Ordering::SeqCst) {
-                self.task_info.status = TaskStatus::Stopped;
-                break;
+            let pages_due = checkpoint_config.every_pages > 0
+                && self.task_info.progress >= last_checkpoint.1 + checkpoint_config.every_pages;
+            let time_due = checkpoint_config.every_secs > 0
+                && last_checkpoint.0.elapsed() >= Duration::from_secs(checkpoint_config.every_secs);
+            if pages_due || time_due {
+                self.save_checkpoint();
+                self.save_stats(&monitor.stats(http_client.retries()));
+                last_checkpoint = (Instant::now(), self.task_info.progress);
+            }
+
+            // 从 receiver 接收更新的任务信息，超时后回到循环开头检查是否需要写检查点
+            match receiver.recv_timeout(tick) {
+                Ok(updated_task_info) => {
+                    self.task_info.copy_progress(&updated_task_info);
+                    if stopping_since.is_none() {
+                        self.update_progress_bar(&pb);
+                    } else {
+                        pb.set_length(self.task_info.discovered as u64);
+                        pb.set_position(self.task_info.progress as u64);
+                    }
+                    self.save_task();
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
             }
         }
 
-        // 等待所有 worker 完成
-        for handle in handles.drain(..) { // 确保迭代完成后清空 handles
+        // 等待所有 worker 退出后再记录任务状态，被取消的 URL 保留在待抓取队列中
+        for handle in handles {
             info!("Wait for thread exiting...");
             handle.join().unwrap();
         }
 
-        if self.task_info.status == TaskStatus::Running {
+        if self.control.is_stopping() {
+            self.task_info.status = TaskStatus::Stopped;
+        } else {
             self.task_info.status = TaskStatus::Finished;
-            pb.finish_with_message("Done!"); // 完成并显示结束消息
+            // 因预算推迟的 URL 仍在待抓取队列中，提高预算后可以用 resume 继续
+            if let Some(limit) = budget.exhausted() {
+                self.task_info.finish_reason = Some(format!("budget exhausted ({})", limit));
+                pb.finish_with_message(format!("Budget exhausted ({})", limit));
+            } else {
+                pb.finish_with_message("Done!"); // 完成并显示结束消息
+            }
         }
 
+        if drained {
+            self.task_info.copy_progress(&task_info.lock().unwrap());
+        } else {
+            // 被取消的请求仍在待抓取队列中，按任务存储重新计算计数
+            self.load_progress();
+        }
         self.task_info.ended_at = Some(Self::current_timestamp());
-        Self::save_task_info(&self.task_info, &self.get_task_dir());
+        self.task_info.cache = http_client.cache_stats();
+        self.task_info.truncated = http_client.download_stats().truncated();
+        self.save_task();
+        self.save_checkpoint();
+        let stats = monitor.stats(http_client.retries());
+        self.save_stats(&stats);
+        self.finish_run(run_id, &stats);
+        self.stats = Some(stats);
+        if self.config.check_links.enabled {
+            self.save_broken_links();
+        }
+        if let Err(e) = control::set_paused(&task_dir, false) {
+            error!("Failed to remove pause file: {}", e);
+        }
+        info!("Task {} ({}) {}", self.task_info.id, self.task_info.name, self.task_info.status);
+        Ok(())
     }
 
-    fn fetch(&self, url: &str) -> Vec<String> {
-        vec![format!("{}/link1", url), format!("{}/link2", url)]
+    // 输出一行运行状态，`started` 为本次运行开始的时间和进度，用于计算速度
+    fn print_status(&self, started: (Instant, u32)) {
+        let task = &self.task_info;
+        let elapsed = started.0.elapsed();
+        let secs = elapsed.as_secs();
+        let rate = task.progress.saturating_sub(started.1) as f64 / elapsed.as_secs_f64().max(1.0);
+        println!(
+            "[{:02}:{:02}:{:02}] {} {}: {}/{} URLs done ({} fetched, {} failed, {} skipped), {} queued, {} in flight, {:.1} URLs/s",
+            secs / 3600, secs / 60 % 60, secs % 60,
+            task.name, task.status, task.progress, task.discovered, task.fetched, task.failed, task.skipped,
+            task.queued, task.in_flight, rate
+        );
     }
 
-    pub fn save_state(&self, file_path: &str) -> std::io::Result<()> {
-        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(file_path)?;
-        for url in &self.visited {
-            writeln!(file, "{}", url)?;
+    // 更新进度条，总数随新发现的 URL 增长，暂停时在消息中显示
+    fn update_progress_bar(&self, pb: &ProgressBar) {
+        let task = &self.task_info;
+        pb.set_length(task.discovered as u64);
+        pb.set_position(task.progress as u64);
+        if task.status == TaskStatus::Paused {
+            pb.set_message(format!("Paused ({} in flight), run `spider resume {}` to continue", task.in_flight, task.id));
+        } else {
+            pb.set_message(format!("{} queued, {} in flight, {} failed, {} skipped", task.queued, task.in_flight, task.failed, task.skipped));
         }
-        Ok(())
     }
 
-    pub fn load_state(&mut self, file_path: &str) -> std::io::Result<()> {
-        let file = File::open(file_path)?;
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            if let Ok(url) = line {
-                self.visited.insert(url);
-            }
+    /// 打印本次运行的汇总信息
+    pub fn print_summary(&self) {
+        let task = &self.task_info;
+        println!("Task {} ({}): {}, {}/{} URLs done", task.id, task.name, task.status, task.progress, task.discovered);
+        println!("Fetched: {}, failed: {}, queued: {}", task.fetched, task.failed, task.queued);
+        if let Some(cache) = &self.task_info.cache {
+            println!("Pages: {} new, {} changed, {} unchanged ({} not modified)", cache.new, cache.changed, cache.unchanged, cache.not_modified);
         }
-        Ok(())
+        println!("Skipped: {}, truncated: {}", self.task_info.skipped, self.task_info.truncated);
+        if let Some(reason) = &self.task_info.finish_reason {
+            println!("Finished early: {}, {} URLs left in the queue", reason, self.task_info.queued);
+        }
+        if let Some(stats) = &self.stats {
+            println!("\nThis run:");
+            stats.print_table();
+        }
+        match self.store.duplicate_clusters(task.id) {
+            Ok(clusters) => print_duplicates(&clusters),
+            Err(e) => error!("Failed to load near duplicates of task {}: {}", task.id, e),
+        }
+        if self.config.check_links.enabled {
+            print_broken_links(&self.broken_links());
+        }
+    }
+
+    /// 任务中的死链，未开启死链检查时为空
+    pub fn broken_links(&self) -> Vec<BrokenLink> {
+        if !self.config.check_links.enabled {
+            return Vec::new();
+        }
+        self.store.broken_links(self.task_info.id).unwrap_or_else(|e| {
+            error!("Failed to load broken links of task {}: {}", self.task_info.id, e);
+            Vec::new()
+        })
     }
 
-    fn generate_task_id() -> u32 {
-        Utc::now().timestamp() as u32
+    // 把死链报告写入任务目录下的 broken-links.csv
+    fn save_broken_links(&self) {
+        let path = Path::new(&self.get_task_dir()).join("broken-links.csv");
+        let links = self.broken_links();
+        match write_broken_links(&links, &path) {
+            Ok(()) => info!("Found {} broken links, wrote {}", links.len(), path.display()),
+            Err(e) => error!("Failed to write {}: {}", path.display(), e),
+        }
     }
 
     fn current_timestamp() -> String {
@@ -216,50 +754,338 @@ impl TaskManager {
         format!("tasks/{}", self.task_info.name)
     }
 
-    fn save_task_info(task_info: &TaskInfo, task_dir: &str) {
-        let task_info_path = format!("{}/task_info.json", task_dir);
-        let file = File::create(task_info_path).unwrap();
-        serde_json::to_writer(file, task_info).unwrap();
+    fn save_task(&self) {
+        if let Err(e) = self.store.save_task(&self.task_info) {
+            error!("Failed to save task {}: {}", self.task_info.id, e);
+        }
+    }
+
+    /// 把本次运行的统计写入任务目录中的 stats.json
+    fn save_stats(&self, stats: &CrawlStats) {
+        if let Err(e) = stats.save(&self.get_task_dir()) {
+            error!("Failed to save stats for task {}: {}", self.task_info.id, e);
+        }
+    }
+
+    // 记录运行结束时的任务状态和统计
+    fn finish_run(&self, run_id: Option<u32>, stats: &CrawlStats) {
+        let Some(run_id) = run_id else {
+            return;
+        };
+        if let Err(e) = self.store.finish_run(run_id, &self.task_info.status.to_string(), stats) {
+            error!("Failed to record run of task {}: {}", self.task_info.id, e);
+        }
+    }
+
+    /// 把任务当前的抓取状态写入任务目录中的检查点文件
+    fn save_checkpoint(&self) {
+        let result = self.store.checkpoint(self.task_info.id)
+            .map_err(|e| e.into())
+            .and_then(|checkpoint| match checkpoint {
+                Some(checkpoint) => checkpoint.save(&self.get_task_dir()),
+                None => Ok(()),
+            });
+        if let Err(e) = result {
+            error!("Failed to save checkpoint for task {}: {}", self.task_info.id, e);
+        }
     }
 
-    // Helper function to read a task's information from its file
-    fn read_task_info(task_dir: &Path) -> Option<TaskInfo> {
-        let task_file = task_dir.join("task_info.json");
-        if task_file.exists() {
-            let file = File::open(task_file).ok()?;
-            let reader = BufReader::new(file);
-            let task_info: TaskInfo = serde_json::from_reader(reader).ok()?;
-            Some(task_info)
+    /// 打印任务的详细报告，`json` 为 true 时输出 JSON，任务不存在时返回 false
+    pub fn show_task(&self, task: &TaskRef, json: bool) -> bool {
+        let Some(report) = self.task_report(task) else {
+            return false;
+        };
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report).expect("Failed to serialize task report"));
         } else {
-            None
+            report.print_table();
         }
+        true
     }
 
-    // Function to list tasks
-    pub fn list_tasks(&self) {
-        let mut table = Table::new();
-        table.add_row(row!["Task ID", "Task Name", "Deep", "Progress", "Status", "Created At", "Ended At"]);
-
-        // Read tasks from the directory
-        if let Ok(entries) = fs::read_dir("tasks") {
-            for entry in entries.flatten() {
-                if entry.file_type().map(|f| f.is_dir()).unwrap_or(false) {
-                    let task_dir = entry.path();
-                    if let Some(task_info) = TaskManager::read_task_info(&task_dir) {
-                        table.add_row(row![
-                            task_info.id,
-                            task_info.name,
-                            task_info.deep,
-                            task_info.progress,
-                            task_info.status,
-                            task_info.created_at.unwrap_or_else(|| "N/A".to_string()),
-                            task_info.ended_at.unwrap_or_else(|| "N/A".to_string())
-                        ]);
-                    }
+    /// 汇总任务的详细报告，任务不存在时返回 None
+    pub fn task_report(&self, task: &TaskRef) -> Option<TaskReport> {
+        let task_id = self.store.resolve(task).expect("Failed to look up task")?;
+        let mut report = self.store.report(task_id).expect("Failed to build task report")?;
+
+        let task_dir = format!("tasks/{}", report.task.name);
+        let cache_dir = format!("{}/cache", task_dir);
+        let checkpoint = format!("{}/{}", task_dir, CHECKPOINT_FILE);
+        report.outputs = Outputs {
+            store: DEFAULT_STORE_PATH.to_string(),
+            cache_dir: std::path::Path::new(&cache_dir).exists().then_some(cache_dir),
+            checkpoint: std::path::Path::new(&checkpoint).exists().then_some(checkpoint),
+            task_dir,
+        };
+        Some(report)
+    }
+
+    /// 设置任务计划，`cron` 为 None 时清除计划；返回下一次运行的时间
+    pub fn schedule_task(&self, task: &TaskRef, cron: Option<String>) -> Result<Option<DateTime<Utc>>, String> {
+        let task_info = self.find_task(task)?;
+        let schedule = ScheduleConfig { cron };
+        schedule.validate()?;
+        let (_, mut config) = self.store.load_task(task_info.id).map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Task {} not found", task))?;
+        config.schedule = schedule;
+        self.store.save_config(task_info.id, &config).map_err(|e| format!("Failed to save task config: {}", e))?;
+        self.store.next_run(task_info.id).map_err(|e| e.to_string())
+    }
+
+    /// 任务的全部运行记录，最新的在前
+    pub fn task_runs(&self, task: &TaskRef) -> Result<Vec<RunRecord>, String> {
+        let task_info = self.find_task(task)?;
+        self.store.load_runs(task_info.id, None).map_err(|e| e.to_string())
+    }
+
+    /// 比较任务两次运行抓取到的页面：`runs` 为空时比较最近两次运行，只有一个时与它之前的一次运行比较
+    pub fn diff_task(&self, task: &TaskRef, runs: &[u32]) -> Result<RunDiff, String> {
+        let task_info = self.find_task(task)?;
+        let recorded = self.store.version_runs(task_info.id).map_err(|e| e.to_string())?;
+        let find = |run_id: u32| {
+            recorded.iter().position(|(id, _)| *id == run_id)
+                .ok_or_else(|| format!("Run {} of task {} ({}) has no recorded pages", run_id, task_info.id, task_info.name))
+        };
+        let (from, to) = match *runs {
+            [] if recorded.len() >= 2 => (recorded.len() - 2, recorded.len() - 1),
+            [] => return Err(format!("Task {} ({}) needs two runs with recorded pages to compare", task_info.id, task_info.name)),
+            [to] => match find(to)? {
+                0 => return Err(format!("Run {} is the first run of task {} ({}) with recorded pages", to, task_info.id, task_info.name)),
+                to => (to - 1, to),
+            },
+            [from, to, ..] => (find(from)?, find(to)?),
+        };
+        self.store.diff_runs(task_info.id, recorded[from].clone(), recorded[to].clone())
+            .map_err(|e| format!("Failed to compare runs: {}", e))
+    }
+
+    /// 导出任务的链接图，`output` 缺省为任务目录下的 graph 目录；`top` 大于 0 时打印 PageRank 最高的页面
+    pub fn export_graph(&self, task: &TaskRef, formats: &[GraphFormat], output: Option<&str>, top: usize) -> Result<(), String> {
+        let task_info = self.find_task(task)?;
+        let graph = self.store.link_graph(task_info.id).map_err(|e| format!("Failed to load link graph: {}", e))?;
+        let dir = output.map(str::to_string).unwrap_or_else(|| format!("tasks/{}/graph", task_info.name));
+        println!("Task {} ({}): {} pages, {} links", task_info.id, task_info.name, graph.nodes.len(), graph.edges.len());
+        for format in formats {
+            let files = graph.export(&task_info.name, Path::new(&dir), *format)
+                .map_err(|e| format!("Failed to write graph to {}: {}", dir, e))?;
+            for file in files {
+                println!("Wrote {}", file.display());
+            }
+        }
+        if top > 0 && !graph.nodes.is_empty() {
+            println!("\nTop pages by PageRank:");
+            graph.print_top(top);
+        }
+        Ok(())
+    }
+
+    /// 删除任务的记录和任务目录，运行中的任务不能删除；`yes` 为 false 时先请求确认
+    pub fn delete_task(&self, task: &TaskRef, yes: bool) -> Result<(), String> {
+        let task_info = self.find_task(task)?;
+        let task_dir = format!("tasks/{}", task_info.name);
+        if let Some(pid) = RunLock::holder(&task_dir) {
+            return Err(format!("Task {} ({}) is running in process {}", task_info.id, task_info.name, pid));
+        }
+        if !yes && !confirm(&format!("Delete task {} ({}) and {}?", task_info.id, task_info.name, task_dir)) {
+            println!("Aborted");
+            return Ok(());
+        }
+
+        self.store.delete_task(task_info.id).map_err(|e| format!("Failed to delete task: {}", e))?;
+        // 任务名无效时（例如旧版本创建的任务）不能确定任务目录在 tasks/ 之下，只删除任务记录
+        if validate_task_name(&task_info.name).is_ok() && Path::new(&task_dir).exists() {
+            std::fs::remove_dir_all(&task_dir).map_err(|e| format!("Failed to remove {}: {}", task_dir, e))?;
+        }
+        println!("Deleted task {} ({})", task_info.id, task_info.name);
+        Ok(())
+    }
+
+    /// 暂停运行中的任务：在任务目录中创建控制文件，运行任务的进程轮询到后停止发起新的请求
+    pub fn pause_task(&self, task: &TaskRef) -> Result<(), String> {
+        let task_info = self.find_task(task)?;
+        let task_dir = format!("tasks/{}", task_info.name);
+        let Some(pid) = RunLock::holder(&task_dir) else {
+            return Err(format!("Task {} ({}) is not running", task_info.id, task_info.name));
+        };
+        control::set_paused(&task_dir, true).map_err(|e| format!("Failed to pause task: {}", e))?;
+        println!("Pausing task {} ({}) in process {}", task_info.id, task_info.name, pid);
+        Ok(())
+    }
+
+    /// 恢复暂停中的任务，任务没有在运行或没有暂停时返回 false
+    pub fn unpause_task(&self, task: &TaskRef) -> bool {
+        let Ok(task_info) = self.find_task(task) else {
+            return false;
+        };
+        let task_dir = format!("tasks/{}", task_info.name);
+        let Some(pid) = RunLock::holder(&task_dir) else {
+            return false;
+        };
+        if !Path::new(&task_dir).join(control::PAUSE_FILE).exists() {
+            return false;
+        }
+        match control::set_paused(&task_dir, false) {
+            Ok(()) => {
+                println!("Resuming paused task {} ({}) in process {}", task_info.id, task_info.name, pid);
+                true
+            }
+            Err(e) => {
+                eprintln!("Failed to resume task: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 把任务打包为 tar.gz 归档，`output` 缺省为当前目录下的 `<任务名>-<ID>.tar.gz`；`delete` 为 true 时归档后删除任务
+    pub fn archive_task(&self, task: &TaskRef, output: Option<&str>, delete: bool) -> Result<(), String> {
+        let task_info = self.find_task(task)?;
+        let task_dir = format!("tasks/{}", task_info.name);
+        if let Some(pid) = RunLock::holder(&task_dir) {
+            return Err(format!("Task {} ({}) is running in process {}", task_info.id, task_info.name, pid));
+        }
+
+        let output = output.map(str::to_string).unwrap_or_else(|| format!("{}-{}.tar.gz", task_info.name, task_info.id));
+        let manifest = archive::create(&self.store, task_info.id, Path::new(&task_dir), Path::new(&output))
+            .map_err(|e| format!("Failed to archive task: {}", e))?;
+        println!("Archived task {} ({}) with {} files to {}", task_info.id, task_info.name, manifest.files.len(), output);
+
+        if delete {
+            self.delete_task(&TaskRef::Id(task_info.id), true)?;
+        }
+        Ok(())
+    }
+
+    /// 从归档导入任务并分配新的 ID，`name` 缺省使用归档中的任务名；任务名已存在时需要 `force` 才会替换
+    pub fn import_task(&self, archive_path: &str, name: Option<&str>, force: bool) -> Result<(), String> {
+        let unpacked = Unpacked::open(Path::new(archive_path), Path::new(&format!("tasks/.import-{}", std::process::id())))
+            .map_err(|e| format!("Failed to read archive {}: {}", archive_path, e))?;
+        let name = name.unwrap_or(&unpacked.manifest.task.name).to_string();
+        // 任务名来自归档清单或命令行，用作任务目录名之前先检查
+        validate_task_name(&name)?;
+
+        if let Some(existing_id) = self.store.resolve(&TaskRef::Name(name.clone())).map_err(|e| e.to_string())? {
+            if !force {
+                return Err(format!("Task named {} already exists (ID {}), use --force to replace it", name, existing_id));
+            }
+            self.delete_task(&TaskRef::Id(existing_id), true)?;
+        }
+
+        let task_info = unpacked.restore(&self.store, &name, Path::new(&format!("tasks/{}", name)))
+            .map_err(|e| format!("Failed to import task: {}", e))?;
+        println!(
+            "Imported task {} ({}) archived at {}: {} pages fetched, {} queued",
+            task_info.id, task_info.name, unpacked.manifest.archived_at, unpacked.manifest.pages.fetched, unpacked.manifest.pages.queued
+        );
+        Ok(())
+    }
+
+    /// 查找任务信息，任务不存在时返回错误
+    pub fn find_task(&self, task: &TaskRef) -> Result<TaskInfo, String> {
+        let task_id = self.store.resolve(task).map_err(|e| e.to_string())?;
+        let loaded = match task_id {
+            Some(task_id) => self.store.load_task(task_id).map_err(|e| e.to_string())?,
+            None => None,
+        };
+        loaded.map(|(task_info, _)| task_info).ok_or_else(|| format!("Task {} not found", task))
+    }
+
+    /// 按选项过滤、排序后列出任务
+    pub fn list_tasks(&self, options: &ListOptions) {
+        let entries = self.query_tasks(options);
+        // 输出可能被管道截断（例如 `| head`），此时忽略写入错误
+        let _ = Self::write_task_list(&entries, options.format, &mut io::stdout().lock());
+    }
+
+    /// 按选项过滤、排序后的任务及其磁盘占用
+    pub fn query_tasks(&self, options: &ListOptions) -> Vec<TaskListEntry> {
+        let created_at = |task_info: &TaskInfo| {
+            task_info.created_at.as_deref().and_then(|created| DateTime::parse_from_rfc3339(created).ok()).map(|created| created.with_timezone(&Utc))
+        };
+
+        let mut tasks: Vec<TaskInfo> = self.store.list_tasks().expect("Failed to list tasks")
+            .into_iter()
+            .filter(|task_info| options.status.as_ref().is_none_or(|status| task_info.status == *status))
+            .filter(|task_info| options.since.is_none_or(|since| created_at(task_info).is_some_and(|created| created >= since)))
+            .collect();
+        match options.sort {
+            // 任务 ID 按创建顺序分配，缺少创建时间的旧任务也能排序
+            ListSort::Created => tasks.sort_by_key(|task_info| (created_at(task_info), task_info.id)),
+            ListSort::Name => tasks.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id))),
+            ListSort::Progress => tasks.sort_by_key(|task_info| (task_info.progress, task_info.id)),
+        }
+        if options.reverse {
+            tasks.reverse();
+        }
+        tasks.truncate(options.limit.unwrap_or(usize::MAX));
+
+        tasks.into_iter()
+            .map(|task| TaskListEntry { disk_usage: dir_size(Path::new(&format!("tasks/{}", task.name))), task })
+            .collect()
+    }
+
+    fn write_task_list(entries: &[TaskListEntry], format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Table => {
+                let mut table = Table::new();
+                table.add_row(row!["Task ID", "Task Name", "Deep", "Progress", "Status", "Disk Usage", "Created At", "Ended At"]);
+                for entry in entries {
+                    let task_info = &entry.task;
+                    table.add_row(row![
+                        task_info.id,
+                        task_info.name,
+                        task_info.deep,
+                        task_info.progress,
+                        task_info.status,
+                        format_bytes(entry.disk_usage),
+                        task_info.created_at.as_deref().unwrap_or("N/A"),
+                        task_info.ended_at.as_deref().unwrap_or("N/A")
+                    ]);
+                }
+                table.print(out)?;
+            }
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, entries)?;
+                writeln!(out)?;
+            }
+            OutputFormat::Csv => {
+                writeln!(out, "id,name,deep,progress,status,disk_usage,created_at,ended_at")?;
+                for entry in entries {
+                    let task_info = &entry.task;
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{},{},{}",
+                        task_info.id,
+                        csv_field(&task_info.name),
+                        task_info.deep,
+                        task_info.progress,
+                        task_info.status,
+                        entry.disk_usage,
+                        task_info.created_at.as_deref().unwrap_or(""),
+                        task_info.ended_at.as_deref().unwrap_or("")
+                    )?;
                 }
             }
         }
+        Ok(())
+    }
+}
+
+// 在终端中请求确认，输入 y 或 yes 时返回 true
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
 
-        table.printstd();
+// 包含逗号、引号或换行的 CSV 字段需要加引号，字段中的引号写两次
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
     }
 }
@@ -0,0 +1,276 @@
+use std::sync::{Arc, Mutex};
+use crate::fetcher::client::{Fetched, HttpClient, RUNTIME};
+use crate::parser::{content_hash, normalize_text, Link, Parser};
+use crate::store::{FetchRecord, TaskStore};
+use crate::utils::logging;
+use std::collections::{HashSet, VecDeque};
+use std::thread;
+use std::time::Instant;
+use crossbeam::channel::Sender;
+use super::{Budget, CrawlMonitor, Duplicate, DuplicateDetector, LinkChecker, RunControl, TaskInfo, TaskStatus, UrlOutcome};
+use super::dedup::DUPLICATE_REASON;
+// 日志宏
+use log::{debug, info, error};
+
+pub struct Worker {
+    http_client: Arc<HttpClient>,
+    to_visit: Arc<Mutex<VecDeque<(String, u32)>>>,
+    visited: Arc<Mutex<HashSet<String>>>,
+    task_info: Arc<Mutex<TaskInfo>>,
+    sender: Sender<TaskInfo>,
+    store: Arc<TaskStore>,
+    budget: Arc<Budget>,
+    monitor: Arc<CrawlMonitor>,
+    control: Arc<RunControl>,
+    dedup: Arc<DuplicateDetector>,
+    link_checker: Option<Arc<LinkChecker>>,
+    task_id: u32,
+}
+
+impl Worker {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        http_client: Arc<HttpClient>,
+        to_visit: Arc<Mutex<VecDeque<(String, u32)>>>,
+        visited: Arc<Mutex<HashSet<String>>>,
+        task_info: Arc<Mutex<TaskInfo>>,
+        sender: Sender<TaskInfo>,
+        store: Arc<TaskStore>,
+        budget: Arc<Budget>,
+        monitor: Arc<CrawlMonitor>,
+        control: Arc<RunControl>,
+        dedup: Arc<DuplicateDetector>,
+        link_checker: Option<Arc<LinkChecker>>,
+    ) -> Self {
+        let task_id = task_info.lock().unwrap().id;
+        Self {
+            http_client,
+            to_visit,
+            visited,
+            task_info,
+            sender,
+            store,
+            budget,
+            monitor,
+            control,
+            dedup,
+            link_checker,
+            task_id,
+        }
+    }
+
+    pub fn run(&self) {
+        logging::set_current_task(Some(self.task_id));
+        let parser = Parser::new();
+        // 任务总预算用尽后停止，剩余的 URL 留在待抓取队列中
+        while !self.control.is_stopping() && !self.budget.is_stopped() {
+            // 从 to_visit 队列中取出一个 URL 及其深度
+            let entry = {
+                let mut to_visit = self.to_visit.lock().unwrap();
+                to_visit.pop_front()
+            };
+
+            // 如果有 URL，调用 crawl 方法进行递归抓取
+            if let Some((url, depth)) = entry {
+                self.crawl(url, depth, parser.clone());
+            } else {
+                // 队列为空，结束抓取
+                break;
+            }
+        }
+        info!("Thread {:?} finished!", thread::current().id());
+    }
+
+    // 递归抓取方法，接收一个 URL 和当前深度
+    fn crawl(&self, url: String, current_depth: u32, parser: Parser) {
+        // 暂停期间在发起下一个请求前等待
+        self.control.wait_while_paused();
+        if self.control.is_stopping() {
+            return;
+        }
+
+        if self.task_info.lock().unwrap().status != TaskStatus::Running {
+            return;
+        }
+
+        // 检查是否超过最大抓取深度
+        if current_depth > self.task_info.lock().unwrap().deep {
+            return;
+        }
+
+        {
+            // 检查 URL 是否已经访问过
+            let mut visited = self.visited.lock().unwrap();
+            if visited.contains(&url) {
+                return;
+            }
+            // 超出预算的 URL 留在待抓取队列中，不标记为已访问
+            if !self.budget.admit(&url) {
+                return;
+            }
+            visited.insert(url.clone());
+        }
+        if let Err(e) = self.store.mark_visited(self.task_id, &url) {
+            error!(url = url.as_str(); "Failed to mark {} as visited: {}", url, e);
+        }
+        self.task_info.lock().unwrap().start_url();
+        self.monitor.start(thread::current().name().unwrap_or("worker"), &url);
+
+        info!(url = url.as_str(); "Thread {:?} fetching: {}", thread::current().id(), url);
+
+        // 使用共享的 HttpClient 实例抓取 URL
+        let http_client = Arc::clone(&self.http_client);
+
+        let started = Instant::now();
+        // 停止等待超时后请求被取消，URL 留在待抓取队列中
+        let Some(fetched) = RUNTIME.block_on(self.control.cancellable(http_client.get_async(&url))) else {
+            info!(url = url.as_str(); "Cancelled fetching {}", url);
+            return;
+        };
+        match fetched {
+            // 按下载限制跳过的页面
+            Ok(Fetched::Skipped(reason)) => {
+                if let Err(e) = self.store.record_skip(self.task_id, &url, &reason) {
+                    error!(url = url.as_str(); "Failed to save skipped URL {}: {}", url, e);
+                }
+                self.budget.release(&url);
+                self.monitor.skipped(&url, current_depth, &reason);
+                self.finish_url(UrlOutcome::Skipped, 0);
+            }
+            Ok(Fetched::Page(page)) => {
+                let elapsed_ms = started.elapsed().as_millis() as u64;
+                self.budget.record_bytes(page.body.len() as u64);
+                // 解析抓取到的页面内容
+                let parsed_url = reqwest::Url::parse(&url).unwrap();
+                let Ok((links, is_content_page, title, text_content)) = parser.parse_content(&page.body, &parsed_url) else {
+                    error!(url = url.as_str(); "Failed to parse content for URL {}", url);
+                    self.record_error(&url, current_depth, "Failed to parse content");
+                    return;
+                };
+
+                if is_content_page {
+                    if let Some(title) = &title {
+                        debug!(url = url.as_str(); "Title: {}", title);
+                    }
+                    if let Some(text_content) = &text_content {
+                        debug!(url = url.as_str(); "Content-Length: {}", text_content.len());
+                    }
+                }
+
+                debug!(url = url.as_str(); "Links-Count: {}", links.len());
+
+                let mut absolute_links = Vec::new();
+                for link in links {
+                    match parsed_url.join(&link.href) {
+                        Ok(absolute_url) => absolute_links.push(Link { href: absolute_url.to_string(), ..link }),
+                        Err(_) => error!(url = url.as_str(); "Failed to resolve link {} relative to base URL {}", link.href, url),
+                    }
+                }
+                let absolute_urls: Vec<String> = absolute_links.iter().map(|link| link.href.clone()).collect();
+
+                let text = text_content.filter(|_| is_content_page).map(|text| normalize_text(&text)).unwrap_or_default();
+                let duplicate = self.dedup.fingerprint(&text)
+                    .filter(|_| (200..300).contains(&page.status))
+                    .and_then(|fingerprint| self.check_duplicate(&url, fingerprint));
+                if duplicate.is_some() && self.dedup.skips_duplicates() {
+                    if let Err(e) = self.store.record_skip(self.task_id, &url, DUPLICATE_REASON) {
+                        error!(url = url.as_str(); "Failed to save skipped URL {}: {}", url, e);
+                    }
+                    self.budget.release(&url);
+                    self.monitor.skipped(&url, current_depth, DUPLICATE_REASON);
+                    self.finish_url(UrlOutcome::Skipped, 0);
+                    return;
+                }
+
+                // 死链检查模式下只跟踪范围内的链接，范围外的链接只检查不抓取
+                let absolute_urls: Vec<String> = match &self.link_checker {
+                    Some(checker) => {
+                        if RUNTIME.block_on(self.control.cancellable(checker.check_async(&absolute_urls))).is_none() {
+                            return;
+                        }
+                        absolute_urls.into_iter().filter(|link| checker.in_scope(link)).collect()
+                    }
+                    None => absolute_urls,
+                };
+
+                // 保存抓取结果，并把未超过深度的子链接写入待抓取队列
+                let record = FetchRecord {
+                    url: url.clone(),
+                    final_url: page.final_url,
+                    depth: current_depth,
+                    status: page.status,
+                    content_type: page.content_type,
+                    content_length: page.body.len(),
+                    title: title.filter(|_| is_content_page),
+                    elapsed_ms,
+                    content_hash: content_hash(&page.body),
+                    text,
+                    links: absolute_links,
+                };
+                let next_links: Vec<(String, u32)> = if current_depth < self.task_info.lock().unwrap().deep {
+                    absolute_urls.iter().map(|link| (link.clone(), current_depth + 1)).collect()
+                } else {
+                    Vec::new()
+                };
+                self.monitor.fetched(&record);
+                let discovered = self.store.record_fetch(self.task_id, &record, &next_links).unwrap_or_else(|e| {
+                    error!(url = url.as_str(); "Failed to save fetch result for {}: {}", url, e);
+                    0
+                });
+                self.finish_url(UrlOutcome::Fetched, discovered);
+
+                // 递归抓取所有子链接
+                for absolute_url in absolute_urls {
+                    if self.control.is_stopping() || self.budget.is_stopped() {
+                        break;
+                    }
+
+                    {
+                        // 确保对 visited 的修改是线程安全的
+                        let visited = self.visited.lock().unwrap();
+                        if !visited.contains(absolute_url.as_str()) {
+                            drop(visited); // 释放锁以避免死锁
+                            self.crawl(absolute_url, current_depth + 1, parser.clone()); // 递归调用，增加深度
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                error!(url = url.as_str(); "Error fetching URL {}: {}", url, e);
+                self.record_error(&url, current_depth, &e.to_string());
+            }
+        }
+    }
+
+    // 与已抓取的页面比较指纹并记录，近似重复时返回最相似的页面
+    fn check_duplicate(&self, url: &str, fingerprint: u64) -> Option<Duplicate> {
+        let duplicate = self.dedup.check(url, fingerprint);
+        if let Some(duplicate) = &duplicate {
+            info!(url = url; "{} is a near duplicate of {} ({:.0}% similar)", url, duplicate.of, duplicate.similarity * 100.0);
+        }
+        if let Err(e) = self.store.record_fingerprint(self.task_id, url, fingerprint, duplicate.as_ref()) {
+            error!(url = url; "Failed to save fingerprint of {}: {}", url, e);
+        }
+        duplicate
+    }
+
+    fn record_error(&self, url: &str, depth: u32, message: &str) {
+        if let Err(e) = self.store.record_error(self.task_id, url, message) {
+            error!(url = url; "Failed to save error for {}: {}", url, e);
+        }
+        self.budget.release(url);
+        self.monitor.failed(url, depth);
+        self.finish_url(UrlOutcome::Failed, 0);
+    }
+
+    // 更新任务计数并通知主线程刷新进度
+    fn finish_url(&self, outcome: UrlOutcome, discovered: u32) {
+        let mut task_info = self.task_info.lock().unwrap();
+        task_info.finish_url(outcome, discovered);
+        // 通知主线程任务进度变化，主线程停止等待后忽略发送失败
+        let _ = self.sender.send(task_info.clone());
+    }
+
+
+
+}
@@ -1,3 +0,0 @@
-mod markdown_writer;
-
-pub use self::markdown_writer::Writer;